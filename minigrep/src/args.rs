@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+
+/// A command-line argument, as split up by [`Parser`].
+#[derive(Debug, PartialEq)]
+pub enum Arg {
+    /// A short flag like `-i`, clustered ones like `-in` come one by one.
+    Short(char),
+    /// A long flag like `--ignore-case`, without the dashes.
+    Long(String),
+    /// Anything else, including everything after `--`.
+    Value(String),
+}
+
+/// Splits command-line arguments into flags and values, the way getopt does.
+///
+/// Flags taking a value fetch it with [`Parser::value`], which accepts
+/// `-A3`, `-A 3`, `--context=3` and `--context 3` alike.
+pub struct Parser {
+    args: VecDeque<String>,
+    /// Rest of a short flag cluster, e.g. `n` after reading `i` from `-in`.
+    shorts: Option<String>,
+    /// Value attached with `=` to the last long flag.
+    long_value: Option<String>,
+    only_values: bool,
+}
+
+impl Parser {
+    pub fn new(args: impl IntoIterator<Item = String>) -> Parser {
+        Parser {
+            args: args.into_iter().collect(),
+            shorts: None,
+            long_value: None,
+            only_values: false,
+        }
+    }
+
+    pub fn next(&mut self) -> Result<Option<Arg>, String> {
+        if let Some(value) = self.long_value.take() {
+            return Err(format!("Unexpected value '{}'", value));
+        }
+        if let Some(mut shorts) = self.shorts.take() {
+            let flag = shorts.remove(0);
+            if !shorts.is_empty() {
+                self.shorts = Some(shorts);
+            }
+            return Ok(Some(Arg::Short(flag)));
+        }
+
+        let arg = match self.args.pop_front() {
+            Some(arg) => arg,
+            None => return Ok(None),
+        };
+        if self.only_values {
+            return Ok(Some(Arg::Value(arg)));
+        }
+        if arg == "--" {
+            self.only_values = true;
+            return self.next();
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            return Ok(Some(match long.split_once('=') {
+                Some((name, value)) => {
+                    self.long_value = Some(value.to_string());
+                    Arg::Long(name.to_string())
+                }
+                None => Arg::Long(long.to_string()),
+            }));
+        }
+        // A lone `-` is a value, conventionally naming stdin
+        if arg.len() > 1 && arg.starts_with('-') {
+            self.shorts = Some(arg[1..].to_string());
+            return self.next();
+        }
+        Ok(Some(Arg::Value(arg)))
+    }
+
+    /// Returns the value of the flag just returned by [`Parser::next`].
+    pub fn value(&mut self) -> Result<String, String> {
+        if let Some(value) = self.long_value.take() {
+            return Ok(value);
+        }
+        if let Some(shorts) = self.shorts.take() {
+            return Ok(shorts);
+        }
+        self.args
+            .pop_front()
+            .ok_or_else(|| "Missing value for flag".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser(args: &[&str]) -> Parser {
+        Parser::new(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn flags_and_values() {
+        let mut parser = parser(&["-in", "--count", "query", "-", "--", "-v"]);
+        assert_eq!(parser.next(), Ok(Some(Arg::Short('i'))));
+        assert_eq!(parser.next(), Ok(Some(Arg::Short('n'))));
+        assert_eq!(parser.next(), Ok(Some(Arg::Long("count".to_string()))));
+        assert_eq!(parser.next(), Ok(Some(Arg::Value("query".to_string()))));
+        assert_eq!(parser.next(), Ok(Some(Arg::Value("-".to_string()))));
+        assert_eq!(parser.next(), Ok(Some(Arg::Value("-v".to_string()))));
+        assert_eq!(parser.next(), Ok(None));
+    }
+
+    #[test]
+    fn flag_values() {
+        let mut parser = parser(&["-A3", "-A", "4", "--context=5", "--context", "6"]);
+        for expected in ["3", "4", "5", "6"] {
+            parser.next().unwrap();
+            assert_eq!(parser.value().as_deref(), Ok(expected));
+        }
+        assert_eq!(parser.next(), Ok(None));
+    }
+
+    #[test]
+    fn unexpected_value() {
+        let mut parser = parser(&["--count=3"]);
+        parser.next().unwrap();
+        assert!(parser.next().is_err());
+    }
+}
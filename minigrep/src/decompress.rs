@@ -0,0 +1,142 @@
+//! Transparent decompression of the files searched with `-z`.
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use lzma_rust2::XzReader;
+use ruzstd::decoding::{FrameDecoder, StreamingDecoder};
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Compression formats, with the magic bytes their data starts with.
+const FORMATS: [(Format, &[u8]); 4] = [
+    (Format::Gzip, b"\x1f\x8b"),
+    (Format::Bzip2, b"BZh"),
+    (Format::Xz, b"\xfd7zXZ\x00"),
+    (Format::Zstd, b"\x28\xb5\x2f\xfd"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+/// Returns a reader of the data of `reader` decompressed, if it starts with
+/// the magic bytes of gzip, bzip2, xz or zstd, or else of the data as is.
+///
+/// All the streams of concatenated data are read, like those of rotated logs
+/// joined with `cat`.
+///
+/// # Errors
+///
+/// Fails if reading the start of the data fails, or if zstd data starts
+/// with an invalid frame header. Other invalid data is reported when read.
+pub fn decompress<'a>(mut reader: impl BufRead + 'a) -> io::Result<Box<dyn BufRead + 'a>> {
+    let start = reader.fill_buf()?;
+    let format = FORMATS
+        .iter()
+        .find(|(_, magic)| start.starts_with(magic))
+        .map(|&(format, _)| format);
+    Ok(match format {
+        None => Box::new(reader),
+        Some(Format::Gzip) => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Some(Format::Bzip2) => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+        Some(Format::Xz) => Box::new(BufReader::new(XzReader::new(reader, true))),
+        Some(Format::Zstd) => {
+            let frame = next_frame(reader)?;
+            Box::new(BufReader::new(MultiZstdDecoder { frame }))
+        }
+    })
+}
+
+/// Reader of all the frames of zstd data, as [`StreamingDecoder`] stops after
+/// the first one.
+struct MultiZstdDecoder<R: BufRead> {
+    /// Decoder of the current frame, if any is left.
+    frame: Option<StreamingDecoder<R, FrameDecoder>>,
+}
+
+impl<R: BufRead> Read for MultiZstdDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let Some(decoder) = &mut self.frame else {
+                return Ok(0);
+            };
+            let read = decoder.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            let reader = self.frame.take().unwrap().into_inner();
+            self.frame = next_frame(reader)?;
+        }
+    }
+}
+
+/// Returns a decoder of the next zstd frame of `reader`, skipping skippable
+/// frames, or `None` at the end of the data.
+///
+/// # Errors
+///
+/// Fails if reading fails or the frame header is invalid.
+fn next_frame<R: BufRead>(mut reader: R) -> io::Result<Option<StreamingDecoder<R, FrameDecoder>>> {
+    loop {
+        let start = reader.fill_buf()?;
+        if start.is_empty() {
+            return Ok(None);
+        }
+        // Skippable frames start with 0x184D2A5?, followed by their length
+        if start.len() >= 4 && start[0] & 0xf0 == 0x50 && start[1..4] == [0x2a, 0x4d, 0x18] {
+            let mut header = [0; 8];
+            reader.read_exact(&mut header)?;
+            let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            io::copy(&mut (&mut reader).take(len.into()), &mut io::sink())?;
+            continue;
+        }
+        return StreamingDecoder::new(reader)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompresses_by_magic_bytes() {
+        let plain: &[u8] = include_bytes!("../tests/fixtures/rust.txt");
+        let compressed: [&[u8]; 5] = [
+            plain,
+            include_bytes!("../tests/fixtures/rust.txt.gz"),
+            include_bytes!("../tests/fixtures/rust.txt.bz2"),
+            include_bytes!("../tests/fixtures/rust.txt.xz"),
+            include_bytes!("../tests/fixtures/rust.txt.zst"),
+        ];
+        for data in compressed {
+            let mut text = Vec::new();
+            decompress(data).unwrap().read_to_end(&mut text).unwrap();
+            assert_eq!(plain, text);
+        }
+    }
+
+    #[test]
+    fn zstd_frames() {
+        // Two frames with a skippable one in between
+        let data: &[u8] = include_bytes!("../tests/fixtures/frames.txt.zst");
+        let mut text = String::new();
+        decompress(data).unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(
+            "Rust:\nsafe, fast, productive.\nPick three.\nbeta rust\n",
+            text
+        );
+    }
+
+    #[test]
+    fn invalid_data() {
+        let mut text = Vec::new();
+        let result = decompress(&b"\x1f\x8bnot gzip"[..])
+            .and_then(|mut reader| reader.read_to_end(&mut text));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! Default flags read from the file named by `MINIGREP_CONFIG`.
+
+use crate::MinigrepError;
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Environment variable naming the file with the default flags.
+pub const CONFIG_VAR: &str = "MINIGREP_CONFIG";
+
+/// Reads the default flags in the config file at `path`, as arguments to put
+/// before those of the command line.
+///
+/// A file ending in `.toml` maps long flag names to their values, e.g.
+/// `smart-case = true` or `glob = ["!*.lock", "!target"]`. Any other file has
+/// an argument per line, like `--smart-case` or `--glob=!*.lock`, skipping
+/// empty lines and those starting with `#`.
+pub fn read(path: &Path) -> Result<Vec<String>, MinigrepError> {
+    let contents = fs::read_to_string(path).map_err(|source| MinigrepError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "toml")
+    {
+        let table: Table = contents.parse().map_err(|err: toml::de::Error| {
+            // The whole message quotes the line, which is too much here
+            let line = err
+                .span()
+                .map_or(1, |span| 1 + contents[..span.start].matches('\n').count());
+            let message = format!(
+                "Invalid TOML on line {}: {}",
+                line,
+                err.message().replace('\n', ", ")
+            );
+            let err = MinigrepError::Usage(message);
+            MinigrepError::Config(Box::new(err))
+        })?;
+        from_table(table).map_err(|err| MinigrepError::Config(Box::new(err.into())))
+    } else {
+        Ok(from_lines(&contents))
+    }
+}
+
+fn from_lines(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+fn from_table(table: Table) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    for (flag, value) in table {
+        // A single value is as good as an array of one
+        let values = match value {
+            Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                Value::Boolean(true) => args.push(format!("--{}", flag)),
+                Value::Boolean(false) => {}
+                Value::String(value) => args.push(format!("--{}={}", flag, value)),
+                Value::Integer(value) => args.push(format!("--{}={}", flag, value)),
+                _ => return Err(format!("Unsupported value for '{}'", flag)),
+            }
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn lines() {
+        let contents = "# Defaults\n--smart-case\n\n  --glob=!*.lock\n-C\n2\n";
+        assert_eq!(
+            from_lines(contents),
+            args(&["--smart-case", "--glob=!*.lock", "-C", "2"])
+        );
+    }
+
+    #[test]
+    fn table() {
+        let table = "smart-case = true\nhidden = false\ncolor = \"always\"\n\
+                     context = 2\nglob = [\"!*.lock\", \"!target\"]\n";
+        assert_eq!(
+            from_table(table.parse().unwrap()).unwrap(),
+            args(&[
+                "--color=always",
+                "--context=2",
+                "--glob=!*.lock",
+                "--glob=!target",
+                "--smart-case",
+            ])
+        );
+        assert!(from_table("[color]\nalways = true".parse().unwrap()).is_err());
+        assert!(from_table("context = 2.5".parse().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+//! The errors of parsing arguments and running searches.
+
+use crate::PatternError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Why [`Config::new`](crate::Config::new) or [`run`](crate::run) failed.
+///
+/// Files that can't be searched are no such failure: they are skipped with a
+/// warning, and make the search end with
+/// [`Outcome::Error`](crate::Outcome::Error).
+#[derive(Debug)]
+pub enum MinigrepError {
+    /// No pattern was given, neither as the first value nor with `-e` or `-f`.
+    MissingQuery,
+    /// `--in-place` was given no files to rewrite.
+    MissingFile,
+    /// A flag minigrep doesn't know, with its dashes.
+    UnknownFlag(String),
+    /// Any other misuse of the arguments, like a flag missing its value or
+    /// flags that don't go together.
+    Usage(String),
+    /// The flags in the config file named by `MINIGREP_CONFIG` are wrong.
+    Config(Box<MinigrepError>),
+    /// A file given as an argument, like the patterns of `-f`, can't be
+    /// read.
+    Io { path: PathBuf, source: io::Error },
+    /// A pattern is not a valid regular expression, or not a valid fuzzy
+    /// pattern.
+    InvalidPattern(PatternError),
+    /// A glob of `-g` or a file type of `-t` is not valid.
+    InvalidFilter(ignore::Error),
+    /// The threads to search files on can't be started.
+    Threads(rayon::ThreadPoolBuildError),
+    /// Printing the results failed.
+    Output(io::Error),
+}
+
+impl fmt::Display for MinigrepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MinigrepError::MissingQuery => write!(f, "Didn't get a query string"),
+            MinigrepError::MissingFile => write!(f, "--in-place needs files to rewrite"),
+            MinigrepError::UnknownFlag(flag) => write!(f, "Unknown flag '{}'", flag),
+            MinigrepError::Usage(message) => write!(f, "{}", message),
+            MinigrepError::Config(err) => write!(f, "{} in the config file", err),
+            MinigrepError::Io { path, source } => {
+                write!(f, "Couldn't read {}: {}", path.display(), source)
+            }
+            MinigrepError::InvalidPattern(err) => write!(f, "Invalid pattern: {}", err),
+            MinigrepError::InvalidFilter(err) => write!(f, "Invalid filter: {}", err),
+            MinigrepError::Threads(err) => write!(f, "Couldn't start threads: {}", err),
+            MinigrepError::Output(err) => write!(f, "Couldn't print the results: {}", err),
+        }
+    }
+}
+
+impl Error for MinigrepError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MinigrepError::Config(err) => Some(err.as_ref()),
+            MinigrepError::Io { source, .. } => Some(source),
+            MinigrepError::InvalidPattern(err) => Some(err),
+            MinigrepError::InvalidFilter(err) => Some(err),
+            MinigrepError::Threads(err) => Some(err),
+            MinigrepError::Output(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Misuses of the arguments found while parsing them.
+impl From<String> for MinigrepError {
+    fn from(message: String) -> MinigrepError {
+        MinigrepError::Usage(message)
+    }
+}
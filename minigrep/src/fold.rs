@@ -0,0 +1,77 @@
+use caseless::Caseless;
+use std::iter;
+use std::ops::Range;
+
+/// Folds the case of `text` with Unicode's full case folding. Unlike
+/// lowercasing, this equates "ß" with "SS" and "ſ" with "s".
+pub fn fold(text: &str) -> String {
+    text.chars().default_case_fold().collect()
+}
+
+/// A line with its case folded, as by [`fold`].
+///
+/// Folding may change the length of text, "İ" folds to "i̇" for example.
+/// Offsets in the folded text are therefore mapped back to whole characters
+/// of the original line.
+pub struct Folded {
+    pub text: String,
+    /// Byte range of the original character each byte of `text` folds from.
+    origins: Vec<Range<usize>>,
+    /// Length of the original line.
+    len: usize,
+}
+
+impl Folded {
+    pub fn new(line: &str) -> Folded {
+        let mut text = String::with_capacity(line.len());
+        let mut origins = Vec::with_capacity(line.len());
+        for (start, c) in line.char_indices() {
+            let origin = start..start + c.len_utf8();
+            for folded in iter::once(c).default_case_fold() {
+                text.push(folded);
+                origins.extend(iter::repeat_n(origin.clone(), folded.len_utf8()));
+            }
+        }
+        Folded {
+            text,
+            origins,
+            len: line.len(),
+        }
+    }
+
+    /// Maps a byte range of the folded text to the whole characters of the
+    /// original line it folds from.
+    pub fn original(&self, range: Range<usize>) -> Range<usize> {
+        let start = self
+            .origins
+            .get(range.start)
+            .map_or(self.len, |origin| origin.start);
+        if range.is_empty() {
+            return start..start;
+        }
+        start..self.origins[range.end - 1].end
+    }
+
+    /// Maps a byte offset of the original line to the folded text.
+    pub fn folded_offset(&self, offset: usize) -> usize {
+        // Sorted by start, as characters fold in order
+        self.origins.partition_point(|origin| origin.start < offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_offsets_back() {
+        // "İ" takes 2 bytes, but folds to 3
+        let folded = Folded::new("xİyß");
+        assert_eq!("xi\u{307}yss", folded.text);
+        assert_eq!(1..3, folded.original(1..2));
+        assert_eq!(4..6, folded.original(5..7));
+        assert_eq!(4..4, folded.original(6..6));
+        assert_eq!(6..6, folded.original(7..7));
+        assert_eq!(5, folded.folded_offset(4));
+    }
+}
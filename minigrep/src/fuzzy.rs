@@ -0,0 +1,247 @@
+//! Approximate matching of literals, within a number of edits.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Longest pattern matched fuzzily, in characters, as bit-parallel matching
+/// keeps a bit per character in a `u64`.
+pub const MAX_LEN: usize = 64;
+
+/// Finds the substrings within a Levenshtein distance of a literal, that is
+/// which take at most that many insertions, deletions or substitutions of
+/// characters to turn into it.
+///
+/// Uses Myers' bit-parallel algorithm, which goes through a line in a single
+/// pass whatever the distance, to find where matches end. Where each one
+/// starts is found going back from its end with the reversed literal.
+pub struct Fuzzy {
+    forward: Masks,
+    backward: Masks,
+    len: usize,
+    max_distance: usize,
+    case_insensitive: bool,
+}
+
+impl Fuzzy {
+    /// # Errors
+    ///
+    /// Fails if `literal` is longer than [`MAX_LEN`] characters, or not
+    /// longer than `max_distance`, as anything would then match.
+    pub fn new(
+        literal: &str,
+        max_distance: usize,
+        case_insensitive: bool,
+    ) -> Result<Fuzzy, String> {
+        let chars: Vec<char> = literal.chars().map(|c| fold(c, case_insensitive)).collect();
+        if chars.len() > MAX_LEN {
+            return Err(format!(
+                "Fuzzy patterns can't be longer than {} characters",
+                MAX_LEN
+            ));
+        }
+        if chars.len() <= max_distance {
+            return Err(format!(
+                "Fuzzy pattern '{}' must be longer than the distance {}",
+                literal, max_distance
+            ));
+        }
+        Ok(Fuzzy {
+            forward: Masks::new(chars.iter().copied()),
+            backward: Masks::new(chars.iter().rev().copied()),
+            len: chars.len(),
+            max_distance,
+            case_insensitive,
+        })
+    }
+
+    /// Returns the byte ranges of the successive non-overlapping matches in
+    /// `line`, along with their distance.
+    ///
+    /// Of the ends of overlapping matches, the last one before the distance
+    /// rises is taken, so that "colour" matches "color" rather than "colo",
+    /// and of their starts the one of the shortest closest match.
+    pub fn find_iter(&self, line: &str) -> Vec<(Range<usize>, usize)> {
+        let chars: Vec<(usize, char)> = line
+            .char_indices()
+            .map(|(offset, c)| (offset, fold(c, self.case_insensitive)))
+            .collect();
+        let offset = |index: usize| chars.get(index).map_or(line.len(), |&(offset, _)| offset);
+        let mut matches = Vec::new();
+        // Index in `chars` where the next match may start
+        let mut from = 0;
+
+        loop {
+            let mut state = State::new(self.len);
+            // Exclusive end and distance of the closest match so far
+            let mut closest: Option<(usize, usize)> = None;
+            for (index, &(_, c)) in chars.iter().enumerate().skip(from) {
+                let distance = state.step(self.forward.get(c), false);
+                // Past the closest match, which ends where the distance rises
+                if closest.is_some_and(|(_, closest)| distance > closest) {
+                    break;
+                }
+                if distance <= self.max_distance {
+                    closest = Some((index + 1, distance));
+                }
+            }
+            let Some((end, _)) = closest else {
+                return matches;
+            };
+
+            // No match is longer than the literal with as many insertions
+            let earliest = from.max(end.saturating_sub(self.len + self.max_distance));
+            let mut state = State::new(self.len);
+            let (mut start, mut distance) = (end, self.len);
+            for index in (earliest..end).rev() {
+                let backward = state.step(self.backward.get(chars[index].1), true);
+                if backward < distance {
+                    (start, distance) = (index, backward);
+                }
+            }
+            matches.push((offset(start)..offset(end), distance));
+            from = end;
+        }
+    }
+}
+
+/// Folds `c` to lowercase if `case_insensitive` is set and it has a single
+/// lowercase character.
+fn fold(c: char, case_insensitive: bool) -> char {
+    if !case_insensitive {
+        return c;
+    }
+    let mut lowercase = c.to_lowercase();
+    match (lowercase.next(), lowercase.next()) {
+        (Some(lower), None) => lower,
+        _ => c,
+    }
+}
+
+/// Bit masks of the positions of each character in a literal.
+struct Masks {
+    ascii: [u64; 128],
+    others: HashMap<char, u64>,
+}
+
+impl Masks {
+    fn new(chars: impl Iterator<Item = char>) -> Masks {
+        let mut masks = Masks {
+            ascii: [0; 128],
+            others: HashMap::new(),
+        };
+        for (index, c) in chars.enumerate() {
+            let bit = 1 << index;
+            match masks.ascii.get_mut(c as usize) {
+                Some(mask) => *mask |= bit,
+                None => *masks.others.entry(c).or_default() |= bit,
+            }
+        }
+        masks
+    }
+
+    fn get(&self, c: char) -> u64 {
+        match self.ascii.get(c as usize) {
+            Some(&mask) => mask,
+            None => self.others.get(&c).copied().unwrap_or(0),
+        }
+    }
+}
+
+/// The last column of the matrix of distances between prefixes of a literal
+/// and of the text, as vertical deltas between successive cells.
+struct State {
+    /// Rows where the distance increases, or decreases, by one from the row
+    /// above.
+    plus: u64,
+    minus: u64,
+    /// Distance between the whole literal and the text so far.
+    distance: usize,
+    /// Bit of the last row.
+    last: u64,
+}
+
+impl State {
+    fn new(len: usize) -> State {
+        State {
+            plus: !0,
+            minus: 0,
+            distance: len,
+            last: 1 << (len - 1),
+        }
+    }
+
+    /// Adds a character of the text, where `eq` has the bits of the rows of
+    /// the literal holding it, and returns the new distance.
+    ///
+    /// Matches may start anywhere in the text, unless `anchored`, where they
+    /// must start at its first character.
+    fn step(&mut self, eq: u64, anchored: bool) -> usize {
+        let (plus, minus) = (self.plus, self.minus);
+        let vertical = eq | minus;
+        let horizontal = (((eq & plus).wrapping_add(plus)) ^ plus) | eq;
+        let mut horizontal_plus = minus | !(horizontal | plus);
+        let mut horizontal_minus = plus & horizontal;
+        if horizontal_plus & self.last != 0 {
+            self.distance += 1;
+        } else if horizontal_minus & self.last != 0 {
+            self.distance -= 1;
+        }
+        // The first row is the distance from the empty literal, which only
+        // grows with the text when anchored
+        horizontal_plus = (horizontal_plus << 1) | anchored as u64;
+        horizontal_minus <<= 1;
+        self.plus = horizontal_minus | !(vertical | horizontal_plus);
+        self.minus = horizontal_plus & vertical;
+        self.distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(literal: &str, max_distance: usize, line: &'a str) -> Vec<(&'a str, usize)> {
+        Fuzzy::new(literal, max_distance, false)
+            .unwrap()
+            .find_iter(line)
+            .into_iter()
+            .map(|(range, distance)| (&line[range], distance))
+            .collect()
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(find("color", 0, "the color"), [("color", 0)]);
+        assert_eq!(
+            find("color", 1, "the colour, the colr"),
+            [("colour", 1), ("colr", 1)]
+        );
+        assert_eq!(find("color", 1, "the colors"), [("color", 0)]);
+        assert_eq!(find("color", 1, "a clour"), []);
+        assert_eq!(find("color", 2, "a clour"), [("clour", 2)]);
+        assert_eq!(find("kitten", 3, "sitting"), [("ittin", 2)]);
+    }
+
+    #[test]
+    fn successive_matches() {
+        assert_eq!(
+            find("abc", 1, "abcabxab"),
+            [("abc", 0), ("abx", 1), ("ab", 1)]
+        );
+        assert_eq!(find("abc", 1, "abcabc"), [("abc", 0), ("abc", 0)]);
+        assert_eq!(find("naïve", 1, "nave naive"), [("nave", 1), ("naive", 1)]);
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let fuzzy = Fuzzy::new("Straße", 1, true).unwrap();
+        assert_eq!(fuzzy.find_iter("STRASE"), [(0..6, 1)]);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Fuzzy::new("ab", 2, false).is_err());
+        assert!(Fuzzy::new(&"a".repeat(MAX_LEN + 1), 1, false).is_err());
+        assert!(Fuzzy::new(&"a".repeat(MAX_LEN), 1, false).is_ok());
+    }
+}
@@ -0,0 +1,81 @@
+//! Events of the `--json` output, one JSON object per line.
+//!
+//! Every event has a `type` and its `data`:
+//!
+//! - `begin`: `{"path"}`, before the first line printed from a file.
+//! - `match`: `{"path", "line_number", "absolute_offset", "lines",
+//!   "submatches"}` for a selected line, where `absolute_offset` is the byte
+//!   offset of the line in the file and each of the `submatches` is
+//!   `{"match", "start", "end"}`, with byte offsets within the line, and
+//!   the `"replacement"` of the match with `--replace`, and its edit
+//!   `"distance"` from the pattern with `--fuzzy`.
+//! - `context`: like `match`, for a line of context.
+//! - `end`: `{"path", "stats"}`, after the last line printed from a file.
+//! - `summary`: `{"stats"}`, once all files are searched.
+//!
+//! Fields are only ever added to this schema, never renamed or removed.
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Begin { path: &'a str },
+    Match(Line<'a>),
+    Context(Line<'a>),
+    End { path: &'a str, stats: FileStats },
+    Summary { stats: Stats },
+}
+
+#[derive(Debug, Serialize)]
+pub struct Line<'a> {
+    pub path: &'a str,
+    pub line_number: usize,
+    pub absolute_offset: usize,
+    /// The line, without its line terminator.
+    pub lines: &'a str,
+    pub submatches: Vec<Submatch<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Submatch<'a> {
+    #[serde(rename = "match")]
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance: Option<usize>,
+}
+
+/// Statistics of a single file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FileStats {
+    /// Number of selected lines.
+    pub matched_lines: usize,
+    /// Number of matches in selected lines.
+    pub matches: usize,
+}
+
+/// Statistics of all files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// Number of files searched.
+    pub searches: usize,
+    /// Number of files with a selected line.
+    pub searches_with_match: usize,
+    pub matched_lines: usize,
+    pub matches: usize,
+}
+
+impl Stats {
+    pub fn add(&mut self, file: FileStats) {
+        self.searches += 1;
+        if file.matched_lines > 0 {
+            self.searches_with_match += 1;
+        }
+        self.matched_lines += file.matched_lines;
+        self.matches += file.matches;
+    }
+}
@@ -1,20 +1,714 @@
-use std::error::Error;
-use std::{env, fs};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.filename)?;
+mod args;
+mod decompress;
+mod defaults;
+mod error;
+mod fold;
+mod fuzzy;
+mod json;
+mod literal;
+mod matcher;
+mod printer;
+mod reader;
+mod replace;
+mod searcher;
+#[cfg(test)]
+mod test_dir;
+mod walk;
+
+use args::{Arg, Parser};
+use decompress::decompress;
+pub use error::MinigrepError;
+use json::{FileStats, Stats};
+use literal::Literals;
+pub use matcher::{Matcher, MatcherBuilder, PatternError};
+use printer::{FileError, PrintSink, Printer};
+pub use reader::{search_reader, search_slice, SearchOptions};
+use reader::{trim_line, Input, Selector};
+use replace::{replace_lines, write_atomically};
+pub use searcher::{Searcher, Sink};
+use walk::{Filters, Walker};
+
+const USAGE: &str = "\
+Usage: minigrep [OPTIONS] PATTERN [PATH]...
+       minigrep [OPTIONS] (-e PATTERN | -f FILE)... [PATH]...
+
+Searches the files and directories at PATH for lines matching the regular
+expression PATTERN. Reads the standard input if PATH is `-` or missing.
+
+Default flags are read from the file named by MINIGREP_CONFIG, either TOML
+like `smart-case = true` if it ends in `.toml`, or an argument per line like
+`--smart-case`. Flags on the command line take precedence.
+
+Exits with 0 if a line is selected, 1 if none is and 2 on errors.
+
+Options:
+  -e, --regexp PATTERN      search for PATTERN, can be given several times
+  -f, --file FILE           search for the patterns in FILE, one per line
+  -F, --fixed-strings       match PATTERN literally
+  -i, --ignore-case         match case-insensitively, like CASE_INSENSITIVE=1,
+                            where only literal patterns match 'ß' with 'SS'
+  -s, --case-sensitive      match case-sensitively, overriding CASE_INSENSITIVE
+  -S, --smart-case          match case-insensitively unless PATTERN has an
+                            uppercase character
+  -w, --word-regexp         only match whole words
+  -x, --line-regexp         only match whole lines
+  -v, --invert-match        select non-matching lines
+  -U, --multiline           match across lines, selecting all the lines of
+                            each match
+      --fuzzy K             match PATTERN literally, but within K inserted,
+                            deleted or substituted characters, each match's
+                            number of which is in the --json output
+  -n, --line-number         prefix each line with its line number
+  -b, --byte-offset         prefix each line with its byte offset in the file
+      --column              prefix each line with its line number and the
+                            column of its first match
+  -A, --after-context NUM   print NUM lines of context after each line
+  -B, --before-context NUM  print NUM lines of context before each line
+  -C, --context NUM         print NUM lines of context around each line
+  -o, --only-matching       only print the matches of selected lines, each on
+                            a line of its own
+  -m, --max-count NUM       stop searching a file after NUM selected lines
+  -c, --count               only print the number of selected lines per file
+  -l, --files-with-matches  only print the names of files with selected lines
+  -L, --files-without-match only print the names of files without selected
+                            lines
+  -r, --replace TEXT        print selected lines with their matches replaced
+                            by TEXT, where `$1` or `${name}` stand for what
+                            capture groups of PATTERN matched
+      --in-place            rewrite the files with the matches replaced
+                            rather than printing them
+      --dry-run             print the changes of --in-place as a diff instead
+                            of rewriting the files
+  -z, --search-zip          search the contents of gzip, bzip2, xz and zstd
+                            compressed files
+      --binary-files TYPE   how to search files with a NUL byte in their first
+                            8 KiB: print `Binary file PATH matches` instead
+                            of their lines (`binary`, default), search them
+                            as `text`, or assume they are `without-match`.
+                            --json and --in-place take `binary` as
+                            `text` and `without-match` respectively
+  -a, --text                like --binary-files=text
+  -I                        like --binary-files=without-match
+  -q, --quiet, --silent     print nothing, only exit with 0 if a line is
+                            selected
+      --json                print one JSON object per line for the start of
+                            each file with selected lines, each line, the end
+                            of each file and the statistics of the search
+      --color WHEN          color matches, file names and line numbers:
+                            `auto` (default) if printing to a terminal and
+                            NO_COLOR is not set, `always` or `never`
+      --hidden              search hidden files and directories
+      --no-ignore           search files ignored by .gitignore, .ignore and
+                            similar files
+  -g, --glob GLOB           only search files matching GLOB, or skip them if
+                            GLOB starts with `!`, can be given several times
+  -t, --type TYPE           only search files of TYPE, like `rust`, can be
+                            given several times
+  -j, --threads NUM         search NUM files at a time, as many as there are
+                            cores by default
+      --sort ORDER          print the results of files as they are searched
+                            (`none`, default), or sorted by `path`
+      --no-config           ignore the config file named by MINIGREP_CONFIG
+      --help                print this help
+  -V, --version             print the version
+";
+
+/// How a search went, when it could run at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Some line was selected.
+    Matched,
+    /// No line was selected.
+    NoMatch,
+    /// Some files could not be searched and were skipped with a warning.
+    /// Takes precedence over the other outcomes, except in quiet mode.
+    Error,
+}
+
+impl Outcome {
+    /// Returns the exit code grep uses for the outcome.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Matched => 0,
+            Outcome::NoMatch => 1,
+            Outcome::Error => 2,
+        }
+    }
+}
+
+pub fn run(config: Config) -> Result<Outcome, MinigrepError> {
+    if config.help {
+        print!("{}", USAGE);
+        return Ok(Outcome::Matched);
+    }
+    if config.version {
+        println!("minigrep {}", env!("CARGO_PKG_VERSION"));
+        return Ok(Outcome::Matched);
+    }
+
+    let searcher = searcher(&config)?;
+
+    // Name the file of each match, unless a single file is searched
+    let with_filename = config.paths.len() > 1 || config.paths.iter().any(|path| path.is_dir());
+
+    let walker = Walker::new(&config.filters).map_err(MinigrepError::InvalidFilter)?;
+    let mut had_errors = false;
+    let mut files = Vec::new();
+    let mut on_error = |path: &Path, err| {
+        warn(path, err);
+        had_errors = true;
+    };
+    for path in &config.paths {
+        if path == Path::new(STDIN) {
+            files.push(path.clone());
+        } else {
+            walker.walk(path, &mut files, &mut on_error);
+        }
+    }
+
+    let color = match config.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|no| no.is_empty())
+        }
+    };
+    let printer = Printer::new(&config, searcher.matcher(), color);
+
+    if config.sort == Sort::Path {
+        files.sort();
+    }
+
+    let mut tally = Tally {
+        matched: false,
+        had_errors,
+        quiet: config.quiet,
+        stats: Stats::default(),
+    };
+    if files.len() == 1 {
+        // Stream the results, as there are no other files to keep apart from
+        let mut out = BufWriter::new(io::stdout().lock());
+        let result = search_file(
+            &config,
+            &searcher,
+            &printer,
+            &files[0],
+            with_filename,
+            &mut out,
+        );
+        tally
+            .record(&files[0], result)
+            .map_err(MinigrepError::Output)?;
+        out.flush().map_err(MinigrepError::Output)?;
+    } else {
+        search_files(
+            &config,
+            &searcher,
+            &printer,
+            &files,
+            with_filename,
+            &mut tally,
+            &mut io::stdout().lock(),
+        )?;
+    }
+    if config.json && !config.quiet {
+        printer
+            .summary(&mut io::stdout().lock(), tally.stats)
+            .map_err(MinigrepError::Output)?;
+    }
 
-    let search = if config.case_sensitive {
-        search_case_sensitive
+    Ok(if tally.had_errors && !(tally.matched && config.quiet) {
+        Outcome::Error
+    } else if tally.matched {
+        Outcome::Matched
     } else {
-        search_case_insensitive
+        Outcome::NoMatch
+    })
+}
+
+/// Builds the searcher of the patterns of `config`, with its options.
+fn searcher(config: &Config) -> Result<Searcher, MinigrepError> {
+    let matcher = MatcherBuilder::new()
+        .case_insensitive(!config.case_sensitive)
+        .case_smart(config.smart_case)
+        .fixed_strings(config.fixed_strings)
+        .word(config.word)
+        .line(config.line)
+        .multiline(config.multiline)
+        .fuzzy(config.fuzzy)
+        .build_many(&config.patterns)
+        .map_err(MinigrepError::InvalidPattern)?;
+    let options = SearchOptions {
+        invert_match: config.invert_match,
+        before_context: config.before_context,
+        after_context: config.after_context,
+        multiline: config.multiline,
+        max_count: config.max_count,
     };
+    Ok(Searcher::new(matcher, options))
+}
+
+/// Searches `files` on a pool of worker threads, printing the results of
+/// each file at once to `out`, in the order the searches end or, with
+/// `--sort=path`, in the order of `files`.
+fn search_files(
+    config: &Config,
+    searcher: &Searcher,
+    printer: &Printer,
+    files: &[PathBuf],
+    with_filename: bool,
+    tally: &mut Tally,
+    out: &mut impl Write,
+) -> Result<(), MinigrepError> {
+    // Zero threads leave the choice to rayon, which uses all cores
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .build()
+        .map_err(MinigrepError::Threads)?;
+    // Set once the remaining files need not be searched
+    let done = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
 
-    for line in search(&config.query, &contents) {
-        println!("{}", line);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            pool.install(|| {
+                files
+                    .par_iter()
+                    .enumerate()
+                    .for_each_with(sender, |sender, (index, path)| {
+                        if done.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        let mut out = Vec::new();
+                        let result =
+                            search_file(config, searcher, printer, path, with_filename, &mut out);
+                        // The receiver is gone if printing failed, and the
+                        // remaining files are skipped
+                        let _ = sender.send((index, result, out));
+                    })
+            })
+        });
+
+        // Owned here to be dropped as soon as printing fails, rather than
+        // once the workers are done
+        let receiver = receiver;
+        // Results received ahead of their turn, by index in `files`
+        let mut pending = BTreeMap::new();
+        let mut next_index = 0;
+        // Whether the output of a file was printed yet
+        let mut printed = false;
+        for (index, result, output) in &receiver {
+            pending.insert(index, (result, output));
+            loop {
+                let (index, (result, output)) = match config.sort {
+                    Sort::Path => match pending.remove_entry(&next_index) {
+                        Some(entry) => entry,
+                        None => break,
+                    },
+                    Sort::None => match pending.pop_first() {
+                        Some(entry) => entry,
+                        None => break,
+                    },
+                };
+                next_index += 1;
+                let separator = if printed && !output.is_empty() {
+                    printer.file_separator(out)
+                } else {
+                    Ok(())
+                };
+                printed |= !output.is_empty();
+                if let Err(err) = separator.and_then(|()| out.write_all(&output)) {
+                    done.store(true, Ordering::Relaxed);
+                    return Err(err);
+                }
+                // Results printed to a buffer cannot fail to print
+                let _ = tally.record(&files[index], result);
+                if tally.matched && config.quiet {
+                    done.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+        Ok(())
+    })
+    .map_err(MinigrepError::Output)
+}
+
+/// What the searches of files found out so far.
+struct Tally {
+    matched: bool,
+    had_errors: bool,
+    quiet: bool,
+    stats: Stats,
+}
+
+impl Tally {
+    /// Records the result of searching the file at `path`, warning about
+    /// failures to read it. Failing to print is fatal, and returned.
+    fn record(&mut self, path: &Path, result: Result<FileStats, FileError>) -> io::Result<()> {
+        match result {
+            Ok(stats) => {
+                self.matched |= stats.matched_lines > 0;
+                self.stats.add(stats);
+            }
+            Err(FileError::Read(err) | FileError::Rewrite(err)) => {
+                // Failures past the match that settles the outcome go unnoticed
+                if !(self.matched && self.quiet) {
+                    warn(path, err);
+                    self.had_errors = true;
+                }
+            }
+            Err(FileError::Write(err)) => return Err(err),
+        }
+        Ok(())
     }
+}
 
-    Ok(())
+/// Path standing for the standard input.
+const STDIN: &str = "-";
+
+/// Searches the file at `path` and prints the results to `out`. Returns
+/// what was selected.
+fn search_file(
+    config: &Config,
+    searcher: &Searcher,
+    printer: &Printer,
+    path: &Path,
+    with_filename: bool,
+    out: &mut impl Write,
+) -> Result<FileStats, FileError> {
+    let input = if path == Path::new(STDIN) {
+        Input::Buffered(Box::new(io::stdin().lock()))
+    } else {
+        // Compressed files are decompressed as a stream
+        Input::open(path, !config.search_zip)?
+    };
+    let mut input = match input {
+        Input::Buffered(reader) if config.search_zip => Input::Buffered(decompress(reader)?),
+        input => input,
+    };
+    // Whether to print that a binary file matches rather than its lines,
+    // which JSON output escapes like any text, and --in-place leaves alone
+    let binary = match config.binary_files {
+        BinaryFiles::Text => false,
+        BinaryFiles::Binary if config.json => false,
+        BinaryFiles::Binary if !config.in_place => input.start()?.contains(&0),
+        // Searched as if empty
+        BinaryFiles::Binary | BinaryFiles::WithoutMatch => {
+            if input.start()?.contains(&0) {
+                input = Input::Buffered(Box::new(io::empty()));
+            }
+            false
+        }
+    };
+    let display_path = if path == Path::new(STDIN) {
+        "(standard input)".to_string()
+    } else {
+        path.display().to_string()
+    };
+
+    if let (true, Some(replacement)) = (config.in_place, &config.replace) {
+        let mut buf = Vec::new();
+        let contents = input.contents(&mut buf)?;
+        let (replaced, changes) = replace_lines(contents, searcher.matcher(), replacement);
+        if config.dry_run {
+            printer
+                .diff(out, &display_path, &changes)
+                .map_err(FileError::Write)?;
+        } else if replaced != contents {
+            write_atomically(path, &replaced).map_err(FileError::Rewrite)?;
+        }
+        return Ok(FileStats {
+            matched_lines: changes.len(),
+            matches: changes.iter().map(|change| change.matches).sum(),
+        });
+    }
+
+    if config.quiet || config.files_with_matches || config.files_without_match || config.count {
+        // Unless counting, one match tells all there is to know
+        let mut counter = Counter {
+            count: 0,
+            limit: if config.count { usize::MAX } else { 1 },
+        };
+        searcher.search_input(input, &mut counter)?;
+        let count = counter.count;
+        if config.quiet {
+            // Nothing to print
+        } else if config.files_with_matches {
+            if count > 0 {
+                printer.path(out, &display_path).map_err(FileError::Write)?;
+            }
+        } else if config.files_without_match {
+            if count == 0 {
+                printer.path(out, &display_path).map_err(FileError::Write)?;
+            }
+        } else {
+            let path = with_filename.then_some(display_path.as_str());
+            printer.count(out, path, count).map_err(FileError::Write)?;
+        }
+        return Ok(FileStats {
+            matched_lines: count,
+            matches: 0,
+        });
+    }
+
+    if binary {
+        // Stop at the first line, which tells all there is to know
+        let mut counter = Counter { count: 0, limit: 1 };
+        searcher.search_input(input, &mut counter)?;
+        if counter.count > 0 {
+            printer
+                .binary_matches(out, &display_path)
+                .map_err(FileError::Write)?;
+        }
+        return Ok(FileStats {
+            matched_lines: counter.count,
+            matches: 0,
+        });
+    }
+
+    // JSON output names the file of every line
+    let line_path = (with_filename || config.json).then_some(display_path.as_str());
+    let mut sink = PrintSink::new(printer, out, &display_path, line_path);
+    searcher.search_input(input, &mut sink)?;
+    Ok(sink.stats)
+}
+
+/// Counts the selected lines, up to `limit`.
+struct Counter {
+    count: usize,
+    limit: usize,
+}
+
+impl Sink for Counter {
+    type Error = io::Error;
+
+    fn line(&mut self, line: &Context) -> io::Result<bool> {
+        if let Context::Match(_) = line {
+            self.count += 1;
+        }
+        Ok(self.count < self.limit)
+    }
+}
+
+/// A line selected by a search, along with where it was found.
+#[derive(Debug, PartialEq)]
+pub struct Match<'a> {
+    /// Number of the line, starting at 1.
+    pub line_no: usize,
+    /// Offset in bytes of the start of the line within the searched text.
+    pub byte_offset: usize,
+    /// Column of the first match within the line, in bytes and starting at
+    /// 1. Lines selected for not matching have column 1.
+    pub column: usize,
+    /// The line, without its line terminator. Matches spanning several
+    /// lines hold them all, separated by their line terminators.
+    pub line: &'a str,
+    /// Byte ranges of the successive matches within `line`. Empty for lines
+    /// selected for not matching and for lines of context.
+    pub matches: Vec<Range<usize>>,
+}
+
+impl<'a> Match<'a> {
+    /// Returns the number of the last line, which is not `line_no` when
+    /// matching several lines.
+    pub fn last_line_no(&self) -> usize {
+        self.line_no + self.line.matches('\n').count()
+    }
+
+    /// Iterates over the text of the matches within the line.
+    pub fn matched(&self) -> impl Iterator<Item = &'a str> + '_ {
+        let line = self.line;
+        self.matches.iter().map(move |range| &line[range.clone()])
+    }
+}
+
+/// Returns the lines of `contents` matched by `matcher`.
+pub fn search<'a>(matcher: &Matcher, contents: &'a str) -> Vec<Match<'a>> {
+    select(contents, |line| matcher.find_iter(line))
+}
+
+/// Returns the lines of `contents` not matched by `matcher`.
+pub fn search_inverted<'a>(matcher: &Matcher, contents: &'a str) -> Vec<Match<'a>> {
+    lines(contents)
+        .filter(|(_, _, line)| !matcher.is_match(line))
+        .map(|(line_no, byte_offset, line)| Match {
+            line_no,
+            byte_offset,
+            column: 1,
+            line,
+            matches: Vec::new(),
+        })
+        .collect()
+}
+
+/// A line selected by [`search_fuzzy`], along with the edit distance of each
+/// of its matches.
+#[derive(Debug, PartialEq)]
+pub struct FuzzyMatch<'a> {
+    pub found: Match<'a>,
+    /// Levenshtein distances from the patterns of the matches in
+    /// `found.matches`, in the same order.
+    pub distances: Vec<usize>,
+}
+
+/// Returns the lines of `contents` matched by `matcher`, along with the edit
+/// distance of each match, which is 0 unless `matcher` is fuzzy.
+///
+/// # Examples
+///
+/// ```
+/// let matcher = minigrep::MatcherBuilder::new()
+///     .fuzzy(Some(1))
+///     .build("color")
+///     .unwrap();
+/// let contents = "\
+/// What color?
+/// Which colour?
+/// No colr at all.";
+///
+/// let distances: Vec<_> = minigrep::search_fuzzy(&matcher, contents)
+///     .into_iter()
+///     .map(|line| (line.found.line_no, line.distances))
+///     .collect();
+/// assert_eq!(vec![(1, vec![0]), (2, vec![1]), (3, vec![1])], distances);
+/// ```
+pub fn search_fuzzy<'a>(matcher: &Matcher, contents: &'a str) -> Vec<FuzzyMatch<'a>> {
+    lines(contents)
+        .filter_map(|(line_no, byte_offset, line)| {
+            let (matches, distances): (Vec<_>, _) =
+                matcher.find_iter_with_distances(line).into_iter().unzip();
+            let column = matches.first()?.start + 1;
+            Some(FuzzyMatch {
+                found: Match {
+                    line_no,
+                    byte_offset,
+                    column,
+                    line,
+                    matches,
+                },
+                distances,
+            })
+        })
+        .collect()
+}
+
+/// A line of a group returned by [`context`].
+#[derive(Debug, PartialEq)]
+pub enum Context<'a> {
+    /// A line selected by the search.
+    Match(Match<'a>),
+    /// A line surrounding a selected one. Its column is always 1.
+    Line(Match<'a>),
+}
+
+/// Adds up to `before` and `after` lines of context around the lines of
+/// `contents` in `selected`, as returned by the `search*` functions.
+///
+/// Returns the lines in groups, where each group holds consecutive lines and
+/// is separated from the next one by at least one line.
+pub fn context<'a>(
+    contents: &'a str,
+    selected: Vec<Match<'a>>,
+    before: usize,
+    after: usize,
+) -> Vec<Vec<Context<'a>>> {
+    let options = SearchOptions {
+        before_context: before,
+        after_context: after,
+        ..SearchOptions::default()
+    };
+    let mut selected: BTreeMap<usize, Match> = selected
+        .into_iter()
+        .map(|found| (found.line_no, found))
+        .collect();
+    // Lines passed on by the selector, as (line number, byte offset, length,
+    // whether selected)
+    let mut passed = Vec::new();
+    let mut selector = Selector::new(options, |line| {
+        let (Context::Match(found) | Context::Line(found)) = &line;
+        let is_match = matches!(line, Context::Match(_));
+        passed.push((found.line_no, found.byte_offset, found.line.len(), is_match));
+        true
+    });
+    for (line_no, byte_offset, line) in lines(contents) {
+        // Any match selects the line, whose own matches are put back below
+        #[allow(clippy::single_range_in_vec_init)]
+        let matches = if selected.contains_key(&line_no) {
+            vec![0..0]
+        } else {
+            Vec::new()
+        };
+        selector.line(line_no, byte_offset, line, matches);
+    }
+
+    let mut groups: Vec<Vec<Context>> = Vec::new();
+    let mut last_line_no = None;
+    for (line_no, byte_offset, len, is_match) in passed {
+        if last_line_no.is_none_or(|last| last + 1 < line_no) {
+            groups.push(Vec::new());
+        }
+        last_line_no = Some(line_no);
+        let line = if is_match {
+            Context::Match(selected.remove(&line_no).unwrap())
+        } else {
+            Context::Line(Match {
+                line_no,
+                byte_offset,
+                column: 1,
+                line: &contents[byte_offset..byte_offset + len],
+                matches: Vec::new(),
+            })
+        };
+        groups.last_mut().unwrap().push(line);
+    }
+    groups
+}
+
+/// Selects the lines of `contents` in which `find` returns the ranges of
+/// some matches.
+fn select<'a>(contents: &'a str, find: impl Fn(&str) -> Vec<Range<usize>>) -> Vec<Match<'a>> {
+    lines(contents)
+        .filter_map(|(line_no, byte_offset, line)| {
+            let matches = find(line);
+            let column = matches.first()?.start + 1;
+            Some(Match {
+                line_no,
+                byte_offset,
+                column,
+                line,
+                matches,
+            })
+        })
+        .collect()
+}
+
+/// Iterates over the lines of `contents` like [`str::lines`], along with
+/// their line numbers and byte offsets.
+fn lines(contents: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    contents
+        .split_inclusive('\n')
+        .scan(0, |byte_offset, line| {
+            let start = *byte_offset;
+            *byte_offset += line.len();
+            Some((start, trim_line(line)))
+        })
+        .enumerate()
+        .map(|(index, (byte_offset, line))| (index + 1, byte_offset, line))
+}
+
+fn warn(path: &Path, err: impl std::fmt::Display) {
+    eprintln!("Skipping {}: {}", path.display(), err);
 }
 
 /// Documentation comment of `search_case_sensitive`
@@ -33,61 +727,499 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
 ///
 /// What a wonderful day shiny day!
 /// Pick three.";
+/// let lines: Vec<&str> = search_case_sensitive(query, contents)
+///     .iter()
+///     .map(|found| found.line)
+///     .collect();
 ///         assert_eq!(
 ///             vec!["What a wonderful day shiny day!", "Pick three."],
-///             search_case_sensitive(query, contents)
+///             lines
 ///         );
 /// ```
 /// Some other explanation.
-pub fn search_case_sensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    contents
-        .lines()
-        .filter(|line| line.contains(query))
-        .collect()
+pub fn search_case_sensitive<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    select(contents, |line| {
+        line.match_indices(query)
+            .map(|(start, found)| start..start + found.len())
+            .collect()
+    })
 }
 
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    let query = query.to_lowercase();
-    contents
-        .lines()
-        .filter(|line| line.to_lowercase().contains(&query))
-        .collect()
+/// Returns the lines of `contents` containing `query`, regardless of case.
+///
+/// Uses Unicode's full case folding, so "ß" matches "SS", and the columns
+/// of matches are those in the original lines.
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    // A single literal is never too many
+    let query = Literals::new(&[query], true).unwrap();
+    select(contents, |line| query.find_iter(line, |_| true))
+}
+
+/// In which order to print the results of several files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    /// As soon as each file is searched.
+    #[default]
+    None,
+    /// By path, at the cost of waiting for earlier files.
+    Path,
+}
+
+/// How to search binary files, those with a NUL byte in their first block.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFiles {
+    /// Like text files, but print that they have selected lines rather than
+    /// the lines themselves.
+    #[default]
+    Binary,
+    /// Like text files.
+    Text,
+    /// Not at all, as if they had no lines.
+    WithoutMatch,
+}
+
+/// When to color the output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only if printing to a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    Always,
+    Never,
 }
 
+#[derive(Debug, Default, PartialEq)]
 pub struct Config {
-    query: String,
-    filename: String,
+    patterns: Vec<String>,
+    paths: Vec<PathBuf>,
     case_sensitive: bool,
+    smart_case: bool,
+    fixed_strings: bool,
+    word: bool,
+    line: bool,
+    multiline: bool,
+    fuzzy: Option<usize>,
+    invert_match: bool,
+    line_number: bool,
+    only_matching: bool,
+    max_count: Option<usize>,
+    count: bool,
+    files_with_matches: bool,
+    files_without_match: bool,
+    quiet: bool,
+    byte_offset: bool,
+    column: bool,
+    before_context: usize,
+    after_context: usize,
+    color: ColorChoice,
+    json: bool,
+    replace: Option<String>,
+    in_place: bool,
+    dry_run: bool,
+    search_zip: bool,
+    binary_files: BinaryFiles,
+    filters: Filters,
+    threads: usize,
+    sort: Sort,
+    help: bool,
+    version: bool,
 }
 
 impl Config {
-    // `&[String]` is to `Vec` as `&str` is to `String`.
-    pub fn new(mut args: env::Args) -> Result<Config, &'static str> {
-        args.next();
-
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a query string"),
+    /// Parses the command-line arguments `args`, starting with the program
+    /// name as in [`env::args`].
+    ///
+    /// Matching is case-insensitive if the `CASE_INSENSITIVE` environment
+    /// variable is set, unless `-i`, `-s` or `-S` says otherwise.
+    ///
+    /// Flags in the config file named by `MINIGREP_CONFIG` come before
+    /// `args`, so the command line has the last word, unless it has
+    /// `--no-config`.
+    pub fn new(args: impl IntoIterator<Item = String>) -> Result<Config, MinigrepError> {
+        let args: Vec<String> = args.into_iter().skip(1).collect();
+        let no_config = args
+            .iter()
+            .take_while(|arg| *arg != "--")
+            .any(|arg| arg == "--no-config");
+        let defaults = match env::var_os(defaults::CONFIG_VAR) {
+            Some(path) if !no_config && !path.is_empty() => defaults::read(Path::new(&path))?,
+            _ => Vec::new(),
         };
+        let case_insensitive_env = env::var("CASE_INSENSITIVE").is_ok();
+        Config::parse(defaults, args, case_insensitive_env)
+    }
 
-        let filename = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a file name"),
+    /// Parses the default flags `defaults`, then the command-line arguments
+    /// `args` without the program name, matching case-insensitively by
+    /// default if `case_insensitive_env`.
+    fn parse(
+        defaults: Vec<String>,
+        args: Vec<String>,
+        case_insensitive_env: bool,
+    ) -> Result<Config, MinigrepError> {
+        let mut config = Config {
+            case_sensitive: !case_insensitive_env,
+            ..Config::default()
         };
+        let mut positional = Vec::new();
+        // Whether the patterns come from flags rather than the first value
+        let mut pattern_flags = false;
 
-        let case_sensitive = env::var("CASE_INSENSITIVE").is_err();
+        let mut parser = Parser::new(defaults);
+        config
+            .flags(&mut parser, &mut positional, &mut pattern_flags)
+            .map_err(|err| MinigrepError::Config(Box::new(err)))?;
+        if let Some(value) = positional.first() {
+            let err = MinigrepError::Usage(format!("Unexpected value '{}'", value));
+            return Err(MinigrepError::Config(Box::new(err)));
+        }
+        config.flags(&mut Parser::new(args), &mut positional, &mut pattern_flags)?;
 
-        Ok(Config {
-            query,
-            filename,
-            case_sensitive,
-        })
+        if config.help || config.version {
+            return Ok(config);
+        }
+        if config.json && (config.count || config.files_with_matches || config.files_without_match)
+        {
+            return Err(MinigrepError::Usage(
+                "--json only prints lines, not counts or file names".to_string(),
+            ));
+        }
+        if config.fuzzy.is_some() && config.multiline {
+            return Err(MinigrepError::Usage(
+                "--fuzzy only matches within lines, not with -U".to_string(),
+            ));
+        }
+        let mut positional = positional.into_iter();
+
+        if !pattern_flags {
+            match positional.next() {
+                Some(arg) => config.patterns.push(arg),
+                None => return Err(MinigrepError::MissingQuery),
+            }
+        }
+
+        // Files and directories to search, the standard input by default
+        config.paths = positional.map(PathBuf::from).collect();
+        if config.paths.is_empty() {
+            if config.in_place {
+                return Err(MinigrepError::MissingFile);
+            }
+            config.paths.push(PathBuf::from(STDIN));
+        }
+
+        if config.in_place {
+            if config.replace.is_none() {
+                return Err(MinigrepError::Usage(
+                    "--in-place needs --replace".to_string(),
+                ));
+            }
+            if config.invert_match
+                || config.multiline
+                || config.only_matching
+                || config.max_count.is_some()
+                || config.count
+                || config.files_with_matches
+                || config.files_without_match
+                || config.quiet
+                || config.json
+                || config.search_zip
+            {
+                return Err(MinigrepError::Usage(
+                    "--in-place can't be combined with -v, -U, -o, -m, -c, -l, -L, -q, -z or --json"
+                        .to_string(),
+                ));
+            }
+            if config.paths.iter().any(|path| path == Path::new(STDIN)) {
+                return Err(MinigrepError::Usage(
+                    "--in-place can't rewrite the standard input".to_string(),
+                ));
+            }
+        } else if config.dry_run {
+            return Err(MinigrepError::Usage(
+                "--dry-run only applies to --in-place".to_string(),
+            ));
+        }
+
+        Ok(config)
     }
+
+    /// Sets the flags `parser` reads, keeping the values in `positional`.
+    fn flags(
+        &mut self,
+        parser: &mut Parser,
+        positional: &mut Vec<String>,
+        pattern_flags: &mut bool,
+    ) -> Result<(), MinigrepError> {
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Arg::Short('e') => {
+                    self.patterns.push(parser.value()?);
+                    *pattern_flags = true;
+                }
+                Arg::Long(flag) if flag == "regexp" => {
+                    self.patterns.push(parser.value()?);
+                    *pattern_flags = true;
+                }
+                Arg::Short('f') => {
+                    self.patterns.extend(read_patterns(&parser.value()?)?);
+                    *pattern_flags = true;
+                }
+                Arg::Long(flag) if flag == "file" => {
+                    self.patterns.extend(read_patterns(&parser.value()?)?);
+                    *pattern_flags = true;
+                }
+                Arg::Short('F') => self.fixed_strings = true,
+                Arg::Long(flag) if flag == "fixed-strings" => self.fixed_strings = true,
+                // The last of the case flags wins
+                Arg::Short('i') => (self.case_sensitive, self.smart_case) = (false, false),
+                Arg::Long(flag) if flag == "ignore-case" => {
+                    (self.case_sensitive, self.smart_case) = (false, false)
+                }
+                Arg::Short('s') => (self.case_sensitive, self.smart_case) = (true, false),
+                Arg::Long(flag) if flag == "case-sensitive" => {
+                    (self.case_sensitive, self.smart_case) = (true, false)
+                }
+                Arg::Short('S') => self.smart_case = true,
+                Arg::Long(flag) if flag == "smart-case" => self.smart_case = true,
+                Arg::Short('w') => self.word = true,
+                Arg::Long(flag) if flag == "word-regexp" => self.word = true,
+                Arg::Short('x') => self.line = true,
+                Arg::Long(flag) if flag == "line-regexp" => self.line = true,
+                Arg::Short('U') => self.multiline = true,
+                Arg::Long(flag) if flag == "multiline" => self.multiline = true,
+                Arg::Long(flag) if flag == "fuzzy" => self.fuzzy = Some(number(parser)?),
+                Arg::Short('v') => self.invert_match = true,
+                Arg::Long(flag) if flag == "invert-match" => self.invert_match = true,
+                Arg::Short('n') => self.line_number = true,
+                Arg::Long(flag) if flag == "line-number" => self.line_number = true,
+                Arg::Short('o') => self.only_matching = true,
+                Arg::Long(flag) if flag == "only-matching" => self.only_matching = true,
+                Arg::Short('m') => self.max_count = Some(number(parser)?),
+                Arg::Long(flag) if flag == "max-count" => self.max_count = Some(number(parser)?),
+                Arg::Short('c') => self.count = true,
+                Arg::Long(flag) if flag == "count" => self.count = true,
+                Arg::Short('l') => self.files_with_matches = true,
+                Arg::Long(flag) if flag == "files-with-matches" => self.files_with_matches = true,
+                Arg::Short('L') => self.files_without_match = true,
+                Arg::Long(flag) if flag == "files-without-match" => self.files_without_match = true,
+                Arg::Short('q') => self.quiet = true,
+                Arg::Long(flag) if flag == "quiet" || flag == "silent" => self.quiet = true,
+                Arg::Short('b') => self.byte_offset = true,
+                Arg::Long(flag) if flag == "byte-offset" => self.byte_offset = true,
+                Arg::Long(flag) if flag == "column" => self.column = true,
+                Arg::Short('A') => self.after_context = number(parser)?,
+                Arg::Long(flag) if flag == "after-context" => self.after_context = number(parser)?,
+                Arg::Short('B') => self.before_context = number(parser)?,
+                Arg::Long(flag) if flag == "before-context" => {
+                    self.before_context = number(parser)?
+                }
+                Arg::Short('C') => {
+                    self.before_context = number(parser)?;
+                    self.after_context = self.before_context;
+                }
+                Arg::Long(flag) if flag == "context" => {
+                    self.before_context = number(parser)?;
+                    self.after_context = self.before_context;
+                }
+                Arg::Long(flag) if flag == "color" || flag == "colour" => {
+                    self.color = match parser.value()?.as_str() {
+                        "auto" => ColorChoice::Auto,
+                        "always" => ColorChoice::Always,
+                        "never" => ColorChoice::Never,
+                        other => {
+                            return Err(MinigrepError::Usage(format!(
+                                "Unknown color choice '{}'",
+                                other
+                            )))
+                        }
+                    }
+                }
+                Arg::Long(flag) if flag == "json" => self.json = true,
+                Arg::Short('r') => self.replace = Some(parser.value()?),
+                Arg::Long(flag) if flag == "replace" => self.replace = Some(parser.value()?),
+                Arg::Long(flag) if flag == "in-place" => self.in_place = true,
+                Arg::Long(flag) if flag == "dry-run" => self.dry_run = true,
+                Arg::Short('z') => self.search_zip = true,
+                Arg::Long(flag) if flag == "search-zip" => self.search_zip = true,
+                Arg::Long(flag) if flag == "binary-files" => {
+                    self.binary_files = match parser.value()?.as_str() {
+                        "binary" => BinaryFiles::Binary,
+                        "text" => BinaryFiles::Text,
+                        "without-match" => BinaryFiles::WithoutMatch,
+                        other => {
+                            return Err(MinigrepError::Usage(format!(
+                                "Unknown binary files type '{}'",
+                                other
+                            )))
+                        }
+                    }
+                }
+                Arg::Short('a') => self.binary_files = BinaryFiles::Text,
+                Arg::Long(flag) if flag == "text" => self.binary_files = BinaryFiles::Text,
+                Arg::Short('I') => self.binary_files = BinaryFiles::WithoutMatch,
+                Arg::Long(flag) if flag == "hidden" => self.filters.hidden = true,
+                Arg::Long(flag) if flag == "no-ignore" => self.filters.no_ignore = true,
+                Arg::Short('g') => self.filters.globs.push(parser.value()?),
+                Arg::Long(flag) if flag == "glob" => self.filters.globs.push(parser.value()?),
+                Arg::Short('t') => self.filters.types.push(parser.value()?),
+                Arg::Long(flag) if flag == "type" => self.filters.types.push(parser.value()?),
+                Arg::Short('j') => self.threads = number(parser)?,
+                Arg::Long(flag) if flag == "threads" => self.threads = number(parser)?,
+                Arg::Long(flag) if flag == "sort" => {
+                    self.sort = match parser.value()?.as_str() {
+                        "none" => Sort::None,
+                        "path" => Sort::Path,
+                        other => {
+                            return Err(MinigrepError::Usage(format!(
+                                "Unknown sort order '{}'",
+                                other
+                            )))
+                        }
+                    }
+                }
+                // Already taken care of by `Config::new`
+                Arg::Long(flag) if flag == "no-config" => {}
+                Arg::Long(flag) if flag == "help" => self.help = true,
+                Arg::Short('V') => self.version = true,
+                Arg::Long(flag) if flag == "version" => self.version = true,
+                Arg::Short(flag) => return Err(MinigrepError::UnknownFlag(format!("-{}", flag))),
+                Arg::Long(flag) => return Err(MinigrepError::UnknownFlag(format!("--{}", flag))),
+                Arg::Value(value) => positional.push(value),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the patterns in the file at `path`, one per line.
+fn read_patterns(path: &str) -> Result<Vec<String>, MinigrepError> {
+    let contents = std::fs::read_to_string(path).map_err(|source| MinigrepError::Io {
+        path: PathBuf::from(path),
+        source,
+    })?;
+    Ok(contents.lines().map(String::from).collect())
+}
+
+/// Parses the value of the last flag as a number.
+fn number(parser: &mut Parser) -> Result<usize, String> {
+    let value = parser.value()?;
+    value
+        .parse()
+        .map_err(|_| format!("Expected a number, got '{}'", value))
 }
 
 #[cfg(test)]
+// Lines with a single match are no mistake
+#[allow(clippy::single_range_in_vec_init)]
 mod tests {
     use super::*;
+    use crate::test_dir::TestDir;
+    use std::fs;
+
+    /// Parses `args` as `Config::new` does, but without reading the config
+    /// file named by `MINIGREP_CONFIG` or `CASE_INSENSITIVE` of whoever runs
+    /// the tests.
+    fn config(args: &[&str]) -> Result<Config, MinigrepError> {
+        let args = args.iter().skip(1).map(|arg| arg.to_string()).collect();
+        Config::parse(Vec::new(), args, false)
+    }
+
+    #[test]
+    fn config_flags() {
+        let config = config(&["minigrep", "-nv", "-i", "--count", "rust", "a", "b"]).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                patterns: vec!["rust".to_string()],
+                paths: vec![PathBuf::from("a"), PathBuf::from("b")],
+                case_sensitive: false,
+                invert_match: true,
+                line_number: true,
+                count: true,
+                ..Config::default()
+            }
+        );
+    }
+
+    #[test]
+    fn config_patterns() {
+        let path = fixture("patterns.txt");
+        let path = path.to_str().unwrap();
+        let flags = config(&["minigrep", "-e", "rust", "-f", path, "-erUsT", "a"]).unwrap();
+        let positional = config(&["minigrep", "rust", "a"]).unwrap();
+
+        assert_eq!(vec!["rust", "fast", "three", "rUsT"], flags.patterns);
+        assert_eq!(vec![PathBuf::from("a")], flags.paths);
+        assert_eq!(positional.patterns, vec!["rust"]);
+    }
+
+    #[test]
+    fn config_case_flags_override_env() {
+        let parse = |args: &[&str]| {
+            let args = args.iter().map(|arg| arg.to_string()).collect();
+            Config::parse(Vec::new(), args, true).unwrap()
+        };
+        let insensitive = parse(&["rust", "a"]);
+        let sensitive = parse(&["-s", "rust", "a"]);
+        assert!(!insensitive.case_sensitive);
+        assert!(sensitive.case_sensitive);
+    }
+
+    #[test]
+    fn config_defaults() {
+        let parse = |defaults: &[&str], args: &[&str]| {
+            let strings = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+            Config::parse(strings(defaults), strings(args), false)
+        };
+        let defaults = ["--color=always", "--smart-case", "-g", "!*.lock", "-n"];
+        let config = parse(&defaults, &["-s", "--color=never", "-g*.rs", "rust"]).unwrap();
+        assert_eq!(config.color, ColorChoice::Never);
+        assert!(config.case_sensitive && !config.smart_case);
+        assert_eq!(config.filters.globs, ["!*.lock", "*.rs"]);
+        assert!(config.line_number);
+        assert_eq!(config.patterns, ["rust"]);
+
+        assert!(parse(&["rust"], &["a"]).is_err());
+        assert!(matches!(
+            parse(&["--colors"], &["rust"]),
+            Err(MinigrepError::Config(err)) if matches!(*err, MinigrepError::UnknownFlag(_))
+        ));
+        assert!(parse(&["-e"], &["rust"]).is_err());
+    }
+
+    #[test]
+    fn config_errors() {
+        assert!(matches!(
+            config(&["minigrep"]),
+            Err(MinigrepError::MissingQuery)
+        ));
+        assert_eq!(
+            config(&["minigrep", "rust"]).unwrap().paths,
+            vec![PathBuf::from(STDIN)]
+        );
+        assert!(matches!(
+            config(&["minigrep", "--bogus", "rust", "a"]),
+            Err(MinigrepError::UnknownFlag(flag)) if flag == "--bogus"
+        ));
+        assert!(matches!(
+            config(&["minigrep", "-f", "missing.txt", "a"]),
+            Err(MinigrepError::Io { path, .. }) if path == Path::new("missing.txt")
+        ));
+        assert!(config(&["minigrep", "--sort=size", "rust", "a"]).is_err());
+        assert!(config(&["minigrep", "-j", "many", "rust", "a"]).is_err());
+        assert!(config(&["minigrep", "--json", "-l", "rust", "a"]).is_err());
+        assert!(config(&["minigrep", "--in-place", "rust", "a"]).is_err());
+        assert!(matches!(
+            config(&["minigrep", "-r", "x", "--in-place", "rust"]),
+            Err(MinigrepError::MissingFile)
+        ));
+        assert!(config(&["minigrep", "-r", "x", "--in-place", "rust", "-"]).is_err());
+        assert!(config(&["minigrep", "-r", "x", "--dry-run", "rust", "a"]).is_err());
+        assert!(config(&["minigrep", "-zr", "x", "--in-place", "rust", "a"]).is_err());
+        assert!(config(&["minigrep", "--fuzzy=1", "-U", "rust", "a"]).is_err());
+        assert!(config(&["minigrep", "--binary-files=none", "rust", "a"]).is_err());
+        assert!(config(&["minigrep", "--help"]).unwrap().help);
+    }
 
     #[test]
     fn case_sensitive() {
@@ -98,7 +1230,13 @@ safe, fast, productive.
 Pick three.";
 
         assert_eq!(
-            vec!["safe, fast, productive."],
+            vec![Match {
+                line_no: 2,
+                byte_offset: 6,
+                column: 16,
+                line: "safe, fast, productive.",
+                matches: vec![15..19],
+            }],
             search_case_sensitive(query, contents)
         );
     }
@@ -112,9 +1250,310 @@ safe, fast, productive.
 Pick three.
 Trust me.";
 
+        let lines: Vec<&str> = search_case_insensitive(query, contents)
+            .iter()
+            .map(|found| found.line)
+            .collect();
+        assert_eq!(vec!["Rust:", "Trust me."], lines);
+    }
+
+    #[test]
+    fn case_insensitive_columns() {
+        let columns: Vec<(usize, usize)> = search_case_insensitive("SS", "İİ ß\nstraße")
+            .iter()
+            .map(|found| (found.line_no, found.column))
+            .collect();
+        assert_eq!(vec![(1, 6), (2, 5)], columns);
+    }
+
+    #[test]
+    fn quiet_outcomes() {
+        let path = fixture("rust.txt");
+        let path = path.to_str().unwrap();
+        let outcome = |args: &[&str]| run(config(args).unwrap()).unwrap();
+
+        assert_eq!(Outcome::Matched, outcome(&["minigrep", "-q", "fast", path]));
+        assert_eq!(Outcome::NoMatch, outcome(&["minigrep", "-q", "slow", path]));
+        assert_eq!(
+            Outcome::Error,
+            outcome(&["minigrep", "-q", "slow", path, "missing"])
+        );
+        assert_eq!(
+            Outcome::Matched,
+            outcome(&["minigrep", "-q", "fast", path, "missing"])
+        );
+    }
+
+    /// Searches `files` in parallel as `run` does with `args`, returning what
+    /// was printed.
+    fn search_all(args: &[&str], files: &[PathBuf]) -> (String, Tally) {
+        let config = config(args).unwrap();
+        let searcher = searcher(&config).unwrap();
+        let printer = Printer::new(&config, searcher.matcher(), false);
+        let mut tally = Tally {
+            matched: false,
+            had_errors: false,
+            quiet: config.quiet,
+            stats: Stats::default(),
+        };
+        let mut out = Vec::new();
+        search_files(
+            &config, &searcher, &printer, files, true, &mut tally, &mut out,
+        )
+        .unwrap();
+        (String::from_utf8(out).unwrap(), tally)
+    }
+
+    /// Searches the file at `path` as `run` does with `args`, returning what
+    /// was printed.
+    fn search_one(args: &[&str], path: &Path) -> String {
+        let config = config(args).unwrap();
+        let searcher = searcher(&config).unwrap();
+        let printer = Printer::new(&config, searcher.matcher(), false);
+        let mut out = Vec::new();
+        assert!(search_file(&config, &searcher, &printer, path, false, &mut out).is_ok());
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Returns the path of the fixture `name`.
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn full_case_folding() {
+        let path = fixture("streets.txt");
+        // Literal regular expressions fold case fully, like fixed strings
+        for args in [
+            ["minigrep", "-i", "STRASSE"],
+            ["minigrep", "-iF", "STRASSE"],
+        ] {
+            assert_eq!(
+                "Hauptstraße 1\nBahnhofstrasse 2\n",
+                search_one(&args, &path)
+            );
+        }
+        assert_eq!(
+            "Bahnhofstrasse 2\n",
+            search_one(&["minigrep", "-i", "STRAS+E"], &path)
+        );
+        assert_eq!(
+            "Haupt<straße 1>\n",
+            search_one(&["minigrep", "-i", "-r", "<$0>", "STRASSE 1"], &path)
+        );
+    }
+
+    #[test]
+    fn context_across_files() {
+        let files = [fixture("poem.txt"), fixture("rust.txt")];
+        let args = [
+            "minigrep",
+            "--sort=path",
+            "-A1",
+            "-e",
+            "Pick",
+            "-e",
+            "frog",
+            "-e",
+            "bog",
+        ];
+        let (poem, rust) = (files[0].display(), files[1].display());
+        let expected = format!(
+            "{0}:How public, like a frog\n{0}-To tell your name the livelong day\n{0}:To an admiring bog!\n--\n{1}:Pick three.\n",
+            poem, rust
+        );
+        assert_eq!(expected, search_all(&args, &files).0);
+        let (out, _) = search_all(&["minigrep", "--sort=path", "-c", "-A1", "Pick"], &files);
+        assert_eq!(format!("{}:0\n{}:1\n", poem, rust), out);
+    }
+
+    #[test]
+    fn parallel_search() {
+        let dir = TestDir::new("parallel");
+        // Later files are quicker to search, to finish out of order
+        let files: Vec<PathBuf> = (0..16)
+            .map(|i| dir.write(&format!("{:02}", i), "rust\n".repeat((16 - i) * 1000)))
+            .collect();
+
+        let (out, tally) = search_all(
+            &["minigrep", "-c", "-j", "4", "--sort=path", "rust"],
+            &files,
+        );
+        let expected: String = files
+            .iter()
+            .enumerate()
+            .map(|(i, path)| format!("{}:{}\n", path.display(), (16 - i) * 1000))
+            .collect();
+        assert_eq!(expected, out);
+        assert!(tally.matched && !tally.had_errors);
+
+        // Unreadable files past the first match go unnoticed with -q
+        let files = [files[0].clone(), dir.path().join("missing")];
+        let (out, tally) = search_all(
+            &["minigrep", "-q", "-j", "4", "--sort=path", "rust"],
+            &files,
+        );
+        assert_eq!("", out);
+        assert!(tally.matched && !tally.had_errors);
+    }
+
+    #[test]
+    fn binary_files() {
+        let dir = TestDir::new("binary");
+        let path = dir.write("binary", "Rust:\0\nsafe, fast, productive.\n");
+        let path = path.to_str().unwrap();
+        let outcome = |args: &[&str]| run(config(args).unwrap()).unwrap();
+
+        assert_eq!(Outcome::Matched, outcome(&["minigrep", "-q", "fast", path]));
+        assert_eq!(
+            Outcome::NoMatch,
+            outcome(&["minigrep", "-qI", "fast", path])
+        );
+        assert_eq!(
+            Outcome::NoMatch,
+            outcome(&["minigrep", "-r", "slow", "--in-place", "fast", path])
+        );
+        assert_eq!(
+            "Rust:\0\nsafe, fast, productive.\n",
+            fs::read_to_string(path).unwrap()
+        );
+        let args = [
+            "minigrep",
+            "--binary-files=text",
+            "-r",
+            "slow",
+            "--in-place",
+        ];
+        assert_eq!(
+            Outcome::Matched,
+            outcome(&[&args[..], &["fast", path]].concat())
+        );
+        assert_eq!(
+            "Rust:\0\nsafe, slow, productive.\n",
+            fs::read_to_string(path).unwrap()
+        );
+    }
+
+    #[test]
+    fn in_place() {
+        let dir = TestDir::new("in-place");
+        let path = dir.write("in-place", "Rust:\r\nsafe, fast, productive.\n");
+        let path = path.to_str().unwrap();
+        let outcome = |args: &[&str]| run(config(args).unwrap()).unwrap();
+
+        let args = [
+            "minigrep",
+            "-r",
+            "${2}, ${1}",
+            "--in-place",
+            r"(\w+), (\w+)",
+        ];
+        let diff = format!(
+            "--- {0}\n+++ {0}\n@@ -2 +2 @@\n-safe, fast, productive.\n+fast, safe, productive.\n",
+            path
+        );
+        assert_eq!(
+            diff,
+            search_one(&[&args[..], &["--dry-run", path]].concat(), Path::new(path))
+        );
+        assert_eq!(
+            "Rust:\r\nsafe, fast, productive.\n",
+            fs::read_to_string(path).unwrap()
+        );
+        assert_eq!(Outcome::Matched, outcome(&[&args[..], &[path]].concat()));
+        assert_eq!(
+            "Rust:\r\nfast, safe, productive.\n",
+            fs::read_to_string(path).unwrap()
+        );
+    }
+
+    #[test]
+    fn context_groups() {
+        let matcher = Matcher::new("x").unwrap();
+        let contents = "1\n2x\n3\n4\n5\n6x\n7x\n8\n9";
+        let line_nos = |before, after| -> Vec<Vec<(usize, bool)>> {
+            context(contents, search(&matcher, contents), before, after)
+                .iter()
+                .map(|group| {
+                    group
+                        .iter()
+                        .map(|line| match line {
+                            Context::Match(found) => (found.line_no, true),
+                            Context::Line(found) => (found.line_no, false),
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            vec![vec![(2, true)], vec![(6, true), (7, true)]],
+            line_nos(0, 0)
+        );
+        assert_eq!(
+            vec![
+                vec![(1, false), (2, true), (3, false)],
+                vec![(5, false), (6, true), (7, true), (8, false)]
+            ],
+            line_nos(1, 1)
+        );
+        assert_eq!(
+            vec![vec![
+                (2, true),
+                (3, false),
+                (4, false),
+                (5, false),
+                (6, true),
+                (7, true),
+                (8, false),
+                (9, false)
+            ]],
+            line_nos(0, 3)
+        );
+        assert_eq!(
+            vec![vec![
+                (1, false),
+                (2, true),
+                (3, false),
+                (4, false),
+                (5, false),
+                (6, true),
+                (7, true)
+            ]],
+            line_nos(3, 0)
+        );
+    }
+
+    #[test]
+    fn positions() {
+        let matcher = Matcher::new("b+").unwrap();
+        let contents = "abc\r\nxyz\n\nbb";
+
         assert_eq!(
-            vec!["Rust:", "Trust me."],
-            search_case_insensitive(query, contents)
+            vec![
+                Match {
+                    line_no: 1,
+                    byte_offset: 0,
+                    column: 2,
+                    line: "abc",
+                    matches: vec![1..2],
+                },
+                Match {
+                    line_no: 4,
+                    byte_offset: 10,
+                    column: 1,
+                    line: "bb",
+                    matches: vec![0..2],
+                }
+            ],
+            search(&matcher, contents)
         );
+        let inverted: Vec<usize> = search_inverted(&matcher, contents)
+            .iter()
+            .map(|found| found.line_no)
+            .collect();
+        assert_eq!(vec![2, 3], inverted);
     }
 }
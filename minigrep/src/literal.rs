@@ -0,0 +1,122 @@
+use crate::fold::{fold, Folded};
+use aho_corasick::{AhoCorasick, BuildError, Input, MatchKind};
+use std::ops::Range;
+
+/// Literals to find in lines in a single pass, with Aho-Corasick.
+///
+/// Unless case-sensitive, literals are found regardless of case using
+/// Unicode's full case folding, so "ß" matches "SS".
+pub struct Literals {
+    automaton: AhoCorasick,
+    case_insensitive: bool,
+}
+
+impl Literals {
+    /// # Errors
+    ///
+    /// Fails if the literals are too many or too long for the automaton.
+    pub fn new<P: AsRef<str>>(
+        literals: &[P],
+        case_insensitive: bool,
+    ) -> Result<Literals, BuildError> {
+        let literals: Vec<String> = literals
+            .iter()
+            .map(|literal| {
+                if case_insensitive {
+                    fold(literal.as_ref())
+                } else {
+                    literal.as_ref().to_string()
+                }
+            })
+            .collect();
+        // Prefer the longest literal among those found at the same offset
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(literals)?;
+        Ok(Literals {
+            automaton,
+            case_insensitive,
+        })
+    }
+
+    /// Returns the byte ranges in `line` of the successive non-overlapping
+    /// occurrences of the literals that `accept` agrees with.
+    pub fn find_iter(
+        &self,
+        line: &str,
+        accept: impl Fn(Range<usize>) -> bool,
+    ) -> Vec<Range<usize>> {
+        let folded = self.case_insensitive.then(|| Folded::new(line));
+        let text = folded.as_ref().map_or(line, |folded| &folded.text);
+        let mut found: Vec<Range<usize>> = Vec::new();
+        let mut from = 0;
+        while let Some(occurrence) = self.automaton.find(Input::new(text).range(from..)) {
+            let range = match &folded {
+                Some(folded) => folded.original(occurrence.range()),
+                None => occurrence.range(),
+            };
+            // Like with regular expressions, an empty occurrence right after
+            // another one does not count
+            let follows = found.last().is_some_and(|last| last.end == range.start);
+            let accepted = !(range.is_empty() && follows) && accept(range.clone());
+            if accepted {
+                found.push(range.clone());
+            }
+            // Skip to the end of the occurrence, or else to the next character
+            let next = if accepted && !range.is_empty() {
+                range.end
+            } else {
+                match line[range.start..].chars().next() {
+                    Some(c) => range.start + c.len_utf8(),
+                    None => break,
+                }
+            };
+            from = match &folded {
+                Some(folded) => folded.folded_offset(next),
+                None => next,
+            };
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_iter(literals: &[&str], case_insensitive: bool, line: &str) -> Vec<Range<usize>> {
+        Literals::new(literals, case_insensitive)
+            .unwrap()
+            .find_iter(line, |_| true)
+    }
+
+    #[test]
+    fn several_literals() {
+        assert_eq!(
+            vec![0..3, 4..10, 11..14],
+            find_iter(&["bar", "foo", "foobar"], false, "foo foobar bar")
+        );
+    }
+
+    #[test]
+    fn full_case_folding() {
+        assert_eq!(vec![3..10], find_iter(&["STRASSE"], true, "Zu Straße"));
+        assert_eq!(vec![2..4, 11..13], find_iter(&["ß"], true, "Maßstab Mass"));
+        assert_eq!(vec![0..2], find_iter(&["ss"], true, "ßs"));
+        // "İ" takes 2 bytes, but folds to 3, while "ı" has no folding
+        assert_eq!(vec![1..3, 7..8], find_iter(&["i"], true, "xİy ıI"));
+    }
+
+    #[test]
+    fn long_line() {
+        // Resuming after each occurrence must not rescan the line from its start
+        let line = "aB".repeat(500_000);
+        assert_eq!(500_000, find_iter(&["ab"], true, &line).len());
+    }
+
+    #[test]
+    fn empty_literal() {
+        assert_eq!(vec![0..0, 1..1, 3..3], find_iter(&[""], false, "aé"));
+        assert_eq!(vec![0..0, 1..2], find_iter(&["", "b"], true, "ab"));
+    }
+}
@@ -1,15 +1,41 @@
-use minigrep::Config;
+use minigrep::{Config, MinigrepError};
 use std::env;
+use std::io;
 use std::process;
 
 fn main() {
     let config = Config::new(env::args()).unwrap_or_else(|err| {
         eprintln!("Problem parsing arguments: {}", err);
-        process::exit(1);
+        process::exit(exit_code(&err));
     });
 
-    if let Err(e) = minigrep::run(config) {
-        eprintln!("Application error: {}", e);
-        process::exit(1);
+    match minigrep::run(config) {
+        Ok(outcome) => process::exit(outcome.exit_code()),
+        Err(e) => {
+            let code = exit_code(&e);
+            // Nothing went wrong that is worth telling
+            if code != 0 {
+                eprintln!("Application error: {}", e);
+            }
+            process::exit(code);
+        }
+    }
+}
+
+/// Returns the exit code for `err`, which is 2 like grep's for errors.
+fn exit_code(err: &MinigrepError) -> i32 {
+    match err {
+        // Whoever reads the output had enough of it, like `head` does
+        MinigrepError::Output(err) if err.kind() == io::ErrorKind::BrokenPipe => 0,
+        MinigrepError::MissingQuery
+        | MinigrepError::MissingFile
+        | MinigrepError::UnknownFlag(_)
+        | MinigrepError::Usage(_)
+        | MinigrepError::Config(_)
+        | MinigrepError::Io { .. }
+        | MinigrepError::InvalidPattern(_)
+        | MinigrepError::InvalidFilter(_)
+        | MinigrepError::Threads(_)
+        | MinigrepError::Output(_) => 2,
     }
 }
@@ -0,0 +1,691 @@
+use crate::fuzzy::Fuzzy;
+use crate::literal::Literals;
+use memchr::memmem::Finder;
+use regex::{Regex, RegexBuilder};
+use regex_syntax::hir::literal::Extractor;
+use regex_syntax::hir::{Hir, HirKind, Literal};
+use regex_syntax::ParserBuilder;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Decides whether a line matches any of the patterns of a query.
+///
+/// Patterns are regular expressions, unless fixed strings are asked for, in
+/// which case they are matched literally. Either way, all patterns are
+/// searched for in a single pass over the line.
+///
+/// Case-insensitive fixed strings use Unicode's full case folding, so that
+/// "ß" matches "SS", and so do regular expressions that only match some
+/// literal text. Other regular expressions only use simple case folding,
+/// which maps single characters to single characters, and so do fuzzy
+/// patterns.
+pub struct Matcher {
+    kind: Kind,
+    /// Whether replacements refer to capture groups, as they do unless the
+    /// patterns are fixed strings.
+    expand: bool,
+    /// Finder of the literal every match starts with, if any.
+    prefix: Option<Finder<'static>>,
+}
+
+enum Kind {
+    Regex(Regex),
+    /// Fixed strings, with what must surround them.
+    Literals(Literals, Boundary),
+    /// Fixed strings within a number of edits, with what must surround them.
+    Fuzzy(Vec<Fuzzy>, Boundary),
+}
+
+/// Why patterns couldn't be built into a [`Matcher`].
+#[derive(Debug)]
+pub enum PatternError {
+    /// A pattern is not a valid regular expression, as told by parsing it on
+    /// its own.
+    Syntax {
+        pattern: String,
+        source: Box<regex_syntax::Error>,
+    },
+    /// The patterns are valid, but can't be compiled together, like when
+    /// they are too big.
+    Regex(regex::Error),
+    /// A fuzzy pattern is longer than 64 characters, or not longer than the
+    /// distance.
+    Fuzzy(String),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatternError::Syntax { pattern, source } => write!(f, "'{}': {}", pattern, source),
+            PatternError::Regex(err) => write!(f, "{}", err),
+            PatternError::Fuzzy(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for PatternError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PatternError::Syntax { source, .. } => Some(source.as_ref()),
+            PatternError::Regex(err) => Some(err),
+            PatternError::Fuzzy(_) => None,
+        }
+    }
+}
+
+impl From<regex::Error> for PatternError {
+    fn from(err: regex::Error) -> PatternError {
+        PatternError::Regex(err)
+    }
+}
+
+impl Matcher {
+    /// Builds a case-sensitive matcher for the regular expression `query`.
+    pub fn new(query: &str) -> Result<Matcher, PatternError> {
+        MatcherBuilder::new().build(query)
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        match &self.kind {
+            Kind::Regex(regex) => regex.is_match(line),
+            Kind::Literals(..) | Kind::Fuzzy(..) => self.find(line).is_some(),
+        }
+    }
+
+    /// Returns the byte range of the first match in `line`.
+    pub fn find(&self, line: &str) -> Option<Range<usize>> {
+        match &self.kind {
+            Kind::Regex(regex) => regex.find(line).map(|found| found.range()),
+            Kind::Literals(..) | Kind::Fuzzy(..) => self.find_iter(line).into_iter().next(),
+        }
+    }
+
+    /// Tells whether the patterns are matched within a number of edits, as
+    /// built with [`MatcherBuilder::fuzzy`].
+    pub fn is_fuzzy(&self) -> bool {
+        matches!(self.kind, Kind::Fuzzy(..))
+    }
+
+    /// Returns a finder of a literal that every match starts with, if there
+    /// is one, to quickly skip over text that cannot match.
+    pub(crate) fn prefix(&self) -> Option<&Finder<'static>> {
+        self.prefix.as_ref()
+    }
+
+    /// Returns the byte ranges of the successive non-overlapping matches in
+    /// `line`, along with what `replacement` replaces them with.
+    ///
+    /// With regular expressions, `$1`, `${1}` or `${name}` in `replacement`
+    /// stand for what a capture group matched, and `$$` for `$`. Fixed
+    /// strings are replaced with `replacement` as is.
+    pub fn replacements(&self, line: &str, replacement: &str) -> Vec<(Range<usize>, String)> {
+        match &self.kind {
+            Kind::Regex(regex) if self.expand => regex
+                .captures_iter(line)
+                .map(|captures| {
+                    let mut text = String::new();
+                    captures.expand(replacement, &mut text);
+                    (captures.get(0).unwrap().range(), text)
+                })
+                .collect(),
+            // Regular expressions matched as literals have no capture group
+            // but the whole match
+            Kind::Literals(..) if self.expand => self
+                .find_iter(line)
+                .into_iter()
+                .map(|range| {
+                    let captures = whole().captures(&line[range.clone()]).unwrap();
+                    let mut text = String::new();
+                    captures.expand(replacement, &mut text);
+                    (range, text)
+                })
+                .collect(),
+            _ => self
+                .find_iter(line)
+                .into_iter()
+                .map(|range| (range, replacement.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Replaces the matches in `line` as told by [`Matcher::replacements`].
+    pub fn replace(&self, line: &str, replacement: &str) -> String {
+        let mut replaced = String::new();
+        let mut end = 0;
+        for (range, text) in self.replacements(line, replacement) {
+            replaced.push_str(&line[end..range.start]);
+            replaced.push_str(&text);
+            end = range.end;
+        }
+        replaced.push_str(&line[end..]);
+        replaced
+    }
+
+    /// Returns the byte ranges of the successive non-overlapping matches in
+    /// `line`.
+    pub fn find_iter(&self, line: &str) -> Vec<Range<usize>> {
+        match &self.kind {
+            Kind::Regex(regex) => regex.find_iter(line).map(|found| found.range()).collect(),
+            Kind::Literals(literals, boundary) => {
+                literals.find_iter(line, |range| boundary.surrounds(line, range))
+            }
+            Kind::Fuzzy(..) => self
+                .find_iter_with_distances(line)
+                .into_iter()
+                .map(|(range, _)| range)
+                .collect(),
+        }
+    }
+
+    /// Returns the byte ranges of the successive non-overlapping matches in
+    /// `line` like [`Matcher::find_iter`], along with their Levenshtein
+    /// distance from the closest pattern, which is 0 unless fuzzy.
+    ///
+    /// Where the matches of several fuzzy patterns overlap, the one starting
+    /// first is taken, or the longest of those starting at the same place.
+    pub fn find_iter_with_distances(&self, line: &str) -> Vec<(Range<usize>, usize)> {
+        let Kind::Fuzzy(fuzzies, boundary) = &self.kind else {
+            return self
+                .find_iter(line)
+                .into_iter()
+                .map(|range| (range, 0))
+                .collect();
+        };
+        let mut found: Vec<(Range<usize>, usize)> = fuzzies
+            .iter()
+            .flat_map(|fuzzy| fuzzy.find_iter(line))
+            .filter(|(range, _)| boundary.surrounds(line, range.clone()))
+            .collect();
+        found.sort_by_key(|(range, _)| (range.start, usize::MAX - range.end));
+        let mut end = 0;
+        found.retain(|(range, _)| {
+            let keep = range.start >= end;
+            if keep {
+                end = range.end;
+            }
+            keep
+        });
+        found
+    }
+}
+
+/// Tells whether `range` of `line` is a whole word like with grep, that is
+/// neither preceded nor followed by a word character.
+fn is_word(line: &str, range: Range<usize>) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    !line[..range.start]
+        .chars()
+        .next_back()
+        .is_some_and(is_word_char)
+        && !line[range.end..].chars().next().is_some_and(is_word_char)
+}
+
+/// Returns a regular expression matching all of any text, to expand
+/// replacements as the regex crate does where there are no capture groups.
+fn whole() -> &'static Regex {
+    static WHOLE: OnceLock<Regex> = OnceLock::new();
+    WHOLE.get_or_init(|| Regex::new(r"(?s)\A.*\z").unwrap())
+}
+
+/// Returns the text matched by the regular expression `pattern`, if it
+/// matches nothing else, like `a\.b` but not `a.b` or `(ab)`.
+fn plain_literal(pattern: &str) -> Option<String> {
+    let literal = |hir: &Hir| match hir.kind() {
+        HirKind::Literal(Literal(bytes)) => std::str::from_utf8(bytes).ok().map(str::to_string),
+        _ => None,
+    };
+    let hir = regex_syntax::parse(pattern).ok()?;
+    match hir.kind() {
+        HirKind::Concat(hirs) => hirs.iter().map(literal).collect(),
+        _ => literal(&hir),
+    }
+}
+
+/// Tells whether the pattern `query` holds an uppercase character, ignoring
+/// those of escape sequences and classes like `\S` or `\p{Greek}` in
+/// regular expressions.
+fn has_uppercase(query: &str, fixed_strings: bool) -> bool {
+    fn has_uppercase_literal(hir: &Hir) -> bool {
+        match hir.kind() {
+            HirKind::Literal(Literal(bytes)) => String::from_utf8_lossy(bytes)
+                .chars()
+                .any(char::is_uppercase),
+            HirKind::Repetition(repetition) => has_uppercase_literal(&repetition.sub),
+            HirKind::Capture(capture) => has_uppercase_literal(&capture.sub),
+            HirKind::Concat(hirs) | HirKind::Alternation(hirs) => {
+                hirs.iter().any(has_uppercase_literal)
+            }
+            HirKind::Empty | HirKind::Class(_) | HirKind::Look(_) => false,
+        }
+    }
+
+    if fixed_strings {
+        return query.chars().any(char::is_uppercase);
+    }
+    // Invalid patterns are reported when building the regular expression
+    regex_syntax::parse(query).is_ok_and(|hir| has_uppercase_literal(&hir))
+}
+
+/// Returns a finder of the literal every match of `hir` starts with, if
+/// there is a single one.
+fn prefix(hir: &Hir) -> Option<Finder<'static>> {
+    match Extractor::new().extract(hir).literals()? {
+        [literal] => prefix_literal(literal.as_bytes()),
+        _ => None,
+    }
+}
+
+/// Returns a finder of `literal` as a prefix of matches, unless it is empty
+/// or could match where invalid UTF-8 was replaced with U+FFFD.
+fn prefix_literal(literal: &[u8]) -> Option<Finder<'static>> {
+    let replacement = "\u{fffd}".as_bytes();
+    let has_replacement = literal
+        .windows(replacement.len())
+        .any(|window| window == replacement);
+    (!literal.is_empty() && !has_replacement).then(|| Finder::new(literal).into_owned())
+}
+
+/// What must surround a match.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Boundary {
+    #[default]
+    None,
+    Word,
+    Line,
+}
+
+impl Boundary {
+    /// Tells whether `range` of `line` is surrounded as it must be.
+    fn surrounds(self, line: &str, range: Range<usize>) -> bool {
+        match self {
+            Boundary::None => true,
+            Boundary::Word => is_word(line, range),
+            Boundary::Line => range == (0..line.len()),
+        }
+    }
+}
+
+/// Configures and builds a [`Matcher`].
+#[derive(Default)]
+pub struct MatcherBuilder {
+    case_insensitive: bool,
+    case_smart: bool,
+    fixed_strings: bool,
+    multiline: bool,
+    boundary: Boundary,
+    fuzzy: Option<usize>,
+}
+
+impl MatcherBuilder {
+    pub fn new() -> MatcherBuilder {
+        MatcherBuilder::default()
+    }
+
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut MatcherBuilder {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Matches case-insensitively if no pattern has an uppercase character,
+    /// like ripgrep's smart case. Takes precedence over
+    /// [`MatcherBuilder::case_insensitive`] when set.
+    pub fn case_smart(&mut self, yes: bool) -> &mut MatcherBuilder {
+        self.case_smart = yes;
+        self
+    }
+
+    /// Matches the patterns literally instead of as regular expressions.
+    pub fn fixed_strings(&mut self, yes: bool) -> &mut MatcherBuilder {
+        self.fixed_strings = yes;
+        self
+    }
+
+    /// Matches the patterns literally, but within `max_distance` insertions,
+    /// deletions or substitutions of characters, if given. Takes precedence
+    /// over [`MatcherBuilder::fixed_strings`], and ignores
+    /// [`MatcherBuilder::multiline`].
+    pub fn fuzzy(&mut self, max_distance: Option<usize>) -> &mut MatcherBuilder {
+        self.fuzzy = max_distance;
+        self
+    }
+
+    /// Matches text spanning several lines, where `^` and `$` match at the
+    /// start and end of every line rather than of the text, though `.` still
+    /// does not match line terminators.
+    pub fn multiline(&mut self, yes: bool) -> &mut MatcherBuilder {
+        self.multiline = yes;
+        self
+    }
+
+    /// Only matches patterns as whole words, that is neither preceded nor
+    /// followed by a letter, digit or underscore.
+    pub fn word(&mut self, yes: bool) -> &mut MatcherBuilder {
+        if yes {
+            self.boundary = Boundary::Word;
+        } else if self.boundary == Boundary::Word {
+            self.boundary = Boundary::None;
+        }
+        self
+    }
+
+    /// Only matches patterns as whole lines. Takes precedence over
+    /// [`MatcherBuilder::word`].
+    pub fn line(&mut self, yes: bool) -> &mut MatcherBuilder {
+        if yes {
+            self.boundary = Boundary::Line;
+        } else if self.boundary == Boundary::Line {
+            self.boundary = Boundary::None;
+        }
+        self
+    }
+
+    /// Builds a matcher for the single pattern `query`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `query` is not a valid regular expression. Never fails with
+    /// fixed strings. Fuzzy patterns fail as told by
+    /// [`MatcherBuilder::build_many`].
+    pub fn build(&self, query: &str) -> Result<Matcher, PatternError> {
+        self.build_many(&[query])
+    }
+
+    /// Builds a matcher for lines matching any of `patterns`, or no line if
+    /// there are no patterns.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a pattern is not a valid regular expression. Never fails
+    /// with fixed strings. Fuzzy patterns fail with a
+    /// [`PatternError::Fuzzy`] if longer than 64 characters, or not longer
+    /// than the distance.
+    pub fn build_many<P: AsRef<str>>(&self, patterns: &[P]) -> Result<Matcher, PatternError> {
+        let literal = self.fixed_strings || self.fuzzy.is_some();
+        let case_insensitive = if self.case_smart {
+            !patterns
+                .iter()
+                .any(|pattern| has_uppercase(pattern.as_ref(), literal))
+        } else {
+            self.case_insensitive
+        };
+        if let Some(max_distance) = self.fuzzy {
+            let fuzzies = patterns
+                .iter()
+                .map(|pattern| Fuzzy::new(pattern.as_ref(), max_distance, case_insensitive))
+                .collect::<Result<_, _>>()
+                .map_err(PatternError::Fuzzy)?;
+            return Ok(Matcher {
+                kind: Kind::Fuzzy(fuzzies, self.boundary),
+                expand: false,
+                prefix: None,
+            });
+        }
+        // Literal regular expressions are searched for as fixed strings when
+        // case-insensitive, to fold their case fully
+        let literals: Option<Vec<String>> = if self.fixed_strings {
+            Some(
+                patterns
+                    .iter()
+                    .map(|pattern| pattern.as_ref().to_string())
+                    .collect(),
+            )
+        } else if case_insensitive {
+            patterns
+                .iter()
+                .map(|pattern| plain_literal(pattern.as_ref()))
+                .collect()
+        } else {
+            None
+        };
+        // Whole lines are only told apart by the regex crate in multiple lines
+        let literals = literals.filter(|_| !(self.multiline && self.boundary == Boundary::Line));
+        if let Some(literals) = literals {
+            // Too many literals for Aho-Corasick are left to the regex crate
+            if let Ok(literals) = Literals::new(&literals, case_insensitive) {
+                let prefix = match patterns {
+                    [pattern] if !case_insensitive => Some(pattern.as_ref()),
+                    _ => None,
+                };
+                let kind = Kind::Literals(literals, self.boundary);
+                return Ok(Matcher {
+                    kind,
+                    expand: !self.fixed_strings,
+                    prefix: prefix.and_then(|pattern| prefix_literal(pattern.as_bytes())),
+                });
+            }
+        }
+
+        let parser = || {
+            ParserBuilder::new()
+                .case_insensitive(case_insensitive)
+                .multi_line(self.multiline)
+                .crlf(self.multiline)
+                .build()
+        };
+        let patterns: Vec<String> = patterns
+            .iter()
+            .map(|pattern| {
+                let pattern = pattern.as_ref();
+                if self.fixed_strings {
+                    return Ok(format!("(?:{})", regex::escape(pattern)));
+                }
+                // Errors of the joined patterns would quote them all, wrapped
+                parser()
+                    .parse(pattern)
+                    .map_err(|source| PatternError::Syntax {
+                        pattern: pattern.to_string(),
+                        source: Box::new(source),
+                    })?;
+                Ok(format!("(?:{})", pattern))
+            })
+            .collect::<Result<_, PatternError>>()?;
+        let pattern = if patterns.is_empty() {
+            // Matches nothing
+            r"[^\s\S]".to_string()
+        } else {
+            patterns.join("|")
+        };
+        let pattern = match self.boundary {
+            Boundary::None => pattern,
+            Boundary::Word => format!(r"\b{{start-half}}(?:{})\b{{end-half}}", pattern),
+            Boundary::Line => format!(r"^(?:{})$", pattern),
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(case_insensitive)
+            .multi_line(self.multiline)
+            .crlf(self.multiline)
+            .build()?;
+        // Valid for the regex crate, and so for its parser
+        let hir = parser().parse(&pattern).ok();
+        Ok(Matcher {
+            kind: Kind::Regex(regex),
+            expand: !self.fixed_strings,
+            prefix: hir.as_ref().and_then(prefix),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regex_anchors_and_alternation() {
+        let matcher = Matcher::new("^(Pick|safe)").unwrap();
+        assert!(matcher.is_match("Pick three."));
+        assert!(matcher.is_match("safe, fast, productive."));
+        assert!(!matcher.is_match("Be safe."));
+    }
+
+    #[test]
+    fn regex_character_class() {
+        let matcher = Matcher::new("[0-9]+ apples").unwrap();
+        assert!(matcher.is_match("I ate 12 apples"));
+        assert!(!matcher.is_match("I ate some apples"));
+    }
+
+    #[test]
+    fn fixed_strings_are_literal() {
+        let matcher = MatcherBuilder::new()
+            .fixed_strings(true)
+            .build("a.c(")
+            .unwrap();
+        assert!(matcher.is_match("x a.c( y"));
+        assert!(!matcher.is_match("abc("));
+    }
+
+    #[test]
+    fn several_patterns() {
+        for fixed_strings in [false, true] {
+            let matcher = MatcherBuilder::new()
+                .fixed_strings(fixed_strings)
+                .build_many(&["fast", "three"])
+                .unwrap();
+            assert_eq!(vec![6..10], matcher.find_iter("safe, fast, productive."));
+            assert!(matcher.is_match("Pick three."));
+            assert!(!matcher.is_match("Rust:"));
+        }
+        let none: [&str; 0] = [];
+        assert!(!MatcherBuilder::new()
+            .build_many(&none)
+            .unwrap()
+            .is_match(""));
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let message = |builder: &MatcherBuilder, patterns: &[&str]| {
+            builder.build_many(patterns).err().unwrap().to_string()
+        };
+        let unclosed = "'a(': regex parse error:\n    a(\n     ^\nerror: unclosed group";
+        assert_eq!(unclosed, message(&MatcherBuilder::new(), &["a("]));
+        assert_eq!(unclosed, message(MatcherBuilder::new().word(true), &["a("]));
+        assert!(message(&MatcherBuilder::new(), &["ok", "b["]).starts_with("'b[': "));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let matcher = MatcherBuilder::new()
+            .case_insensitive(true)
+            .build("rUsT")
+            .unwrap();
+        assert!(matcher.is_match("Trust me."));
+    }
+
+    #[test]
+    fn word() {
+        let matcher = MatcherBuilder::new().word(true).build("rust").unwrap();
+        assert!(matcher.is_match("I rust."));
+        assert!(!matcher.is_match("I trust."));
+    }
+
+    #[test]
+    fn word_not_starting_with_word_character() {
+        let matcher = MatcherBuilder::new().word(true).build("-v").unwrap();
+        assert!(matcher.is_match("grep -v rust"));
+        assert!(!matcher.is_match("grep -vn rust"));
+    }
+
+    #[test]
+    fn line() {
+        for fixed_strings in [false, true] {
+            let matcher = MatcherBuilder::new()
+                .case_insensitive(true)
+                .fixed_strings(fixed_strings)
+                .line(true)
+                .build("pick three.")
+                .unwrap();
+            assert!(matcher.is_match("Pick three."));
+            assert!(!matcher.is_match("Pick three. Or four."));
+        }
+    }
+
+    #[test]
+    fn smart_case() {
+        let smart = |query: &str| MatcherBuilder::new().case_smart(true).build(query).unwrap();
+        assert!(smart("rust").is_match("Rust"));
+        assert!(!smart("Rust").is_match("rust"));
+        assert!(smart(r"\Srust\p{Greek}").is_match("TRUSTα"));
+        assert!(!smart("Rust").is_match("RUST"));
+    }
+
+    #[test]
+    fn case_folded_fixed_strings() {
+        let matcher = MatcherBuilder::new()
+            .case_insensitive(true)
+            .fixed_strings(true)
+            .word(true)
+            .build("STRASSE")
+            .unwrap();
+        assert_eq!(vec![13..20], matcher.find_iter("Straßenbahn Straße"));
+    }
+
+    #[test]
+    fn prefixes() {
+        let prefix = |builder: &MatcherBuilder, pattern| {
+            let matcher = builder.build(pattern).unwrap();
+            matcher.prefix().map(|finder| finder.needle().to_vec())
+        };
+        let regex = MatcherBuilder::new();
+        assert_eq!(Some(b"fo".to_vec()), prefix(&regex, "fo+bar"));
+        assert_eq!(
+            Some(b"a.c".to_vec()),
+            prefix(MatcherBuilder::new().word(true), r"a\.c")
+        );
+        assert_eq!(None, prefix(&regex, "foo|bar"));
+        assert_eq!(None, prefix(&regex, r"\w+"));
+        assert_eq!(
+            None,
+            prefix(MatcherBuilder::new().case_insensitive(true), "foo")
+        );
+        assert_eq!(
+            Some(b"a.c".to_vec()),
+            prefix(MatcherBuilder::new().fixed_strings(true), "a.c")
+        );
+    }
+
+    #[test]
+    fn replace() {
+        let matcher = Matcher::new(r"(?P<word>\w+)@(\w+)").unwrap();
+        assert_eq!(
+            "b at a, $ d at c",
+            matcher.replace("a@b, $ c@d", "${2} at $word")
+        );
+        let matcher = MatcherBuilder::new()
+            .fixed_strings(true)
+            .build("a@b")
+            .unwrap();
+        assert_eq!("$1, c@d", matcher.replace("a@b, c@d", "$1"));
+    }
+
+    #[test]
+    fn fuzzy() {
+        let matcher = MatcherBuilder::new()
+            .fuzzy(Some(1))
+            .case_smart(true)
+            .build_many(&["color", "flavor"])
+            .unwrap();
+        assert!(matcher.is_fuzzy());
+        assert_eq!(
+            vec![(0..6, 1), (11..18, 1)],
+            matcher.find_iter_with_distances("Colour and FLAVOUR")
+        );
+        assert_eq!("a hue", matcher.replace("a colr", "hue"));
+
+        let matcher = MatcherBuilder::new()
+            .fuzzy(Some(1))
+            .word(true)
+            .build("color")
+            .unwrap();
+        assert_eq!(vec![0..6], matcher.find_iter("colour discolored"));
+        let matcher = Matcher::new("colou?r").unwrap();
+        assert_eq!(vec![(0..5, 0)], matcher.find_iter_with_distances("color"));
+
+        assert!(matches!(
+            MatcherBuilder::new().fuzzy(Some(3)).build("abc"),
+            Err(PatternError::Fuzzy(_))
+        ));
+    }
+}
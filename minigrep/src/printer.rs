@@ -0,0 +1,588 @@
+use crate::json::{Event, FileStats, Line, Stats, Submatch};
+use crate::reader::trim_line;
+use crate::replace::Change;
+use crate::{Config, Context, Matcher, Sink};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// Colors of grep, as SGR parameters of ANSI escape sequences.
+const MATCH_COLOR: &str = "1;31";
+const PATH_COLOR: &str = "35";
+const NUMBER_COLOR: &str = "32";
+const SEPARATOR_COLOR: &str = "36";
+
+/// Prints search results in the format asked for by a [`Config`].
+pub struct Printer<'a> {
+    config: &'a Config,
+    matcher: &'a Matcher,
+    color: bool,
+}
+
+impl<'a> Printer<'a> {
+    /// Creates a printer, highlighting the matches of `matcher` and the
+    /// prefixes of lines with ANSI colors if `color` is set.
+    pub fn new(config: &'a Config, matcher: &'a Matcher, color: bool) -> Printer<'a> {
+        Printer {
+            config,
+            matcher,
+            color,
+        }
+    }
+
+    /// Prints a file name on its own line.
+    pub fn path(&self, out: &mut impl Write, path: &str) -> io::Result<()> {
+        self.paint(out, PATH_COLOR, path)?;
+        writeln!(out)
+    }
+
+    /// Prints that the binary file at `path` has selected lines, in place of
+    /// them, like grep.
+    pub fn binary_matches(&self, out: &mut impl Write, path: &str) -> io::Result<()> {
+        writeln!(out, "Binary file {} matches", path)
+    }
+
+    /// Prints the number of selected lines, prefixed with `path` if given.
+    pub fn count(&self, out: &mut impl Write, path: Option<&str>, count: usize) -> io::Result<()> {
+        if let Some(path) = path {
+            self.paint(out, PATH_COLOR, path)?;
+            self.paint(out, SEPARATOR_COLOR, ":")?;
+        }
+        writeln!(out, "{}", count)
+    }
+
+    /// Prints the separator between groups of lines of context, which JSON
+    /// output has no need for.
+    pub fn group_separator(&self, out: &mut impl Write) -> io::Result<()> {
+        if self.config.json {
+            return Ok(());
+        }
+        self.paint(out, SEPARATOR_COLOR, "--")?;
+        writeln!(out)
+    }
+
+    /// Prints the separator between the lines of two files, which grep
+    /// prints like that between groups when there are lines of context.
+    pub fn file_separator(&self, out: &mut impl Write) -> io::Result<()> {
+        let config = self.config;
+        let with_context = config.before_context > 0 || config.after_context > 0;
+        // Only lines of context are separated, not what stands for files
+        let lines = !(config.quiet
+            || config.count
+            || config.files_with_matches
+            || config.files_without_match
+            || config.in_place
+            || config.only_matching);
+        if with_context && lines {
+            self.group_separator(out)?;
+        }
+        Ok(())
+    }
+
+    /// Prints a line returned by [`crate::search_reader`], prefixed with
+    /// `path` if given.
+    ///
+    /// Like grep, the prefixes of context lines are separated by `-` rather
+    /// than `:`, and lack a column. With `--replace`, the matches of selected
+    /// lines are replaced.
+    pub fn line(&self, out: &mut impl Write, path: Option<&str>, line: &Context) -> io::Result<()> {
+        if self.config.json {
+            return self.json_line(out, path.unwrap_or_default(), line);
+        }
+        let (found, separator) = match line {
+            Context::Match(found) => (found, ":"),
+            Context::Line(found) => (found, "-"),
+        };
+        if self.config.only_matching {
+            return self.matches(out, path, line);
+        }
+        let replaces = self.replaces(line);
+        let mut pieces: VecDeque<_> = if self.color || replaces {
+            self.pieces(line).into()
+        } else {
+            VecDeque::new()
+        };
+        // Offset in the line up to which it is printed
+        let mut printed = 0;
+
+        // Lines of multi-line matches are printed with a prefix each
+        let count = found.line.split('\n').count();
+        let mut start = 0;
+        for (index, text) in found.line.split('\n').enumerate() {
+            let next = start + text.len() + 1;
+            let end = if index + 1 < count {
+                start + trim_line(text).len()
+            } else {
+                start + text.len()
+            };
+            let column = (index == 0 && separator == ":").then_some(found.column);
+            let byte_offset = found.byte_offset + start;
+            self.prefix(
+                out,
+                path,
+                separator,
+                found.line_no + index,
+                column,
+                byte_offset,
+            )?;
+            printed = printed.max(start);
+            while let Some((range, text)) = pieces.pop_front() {
+                if range.start >= next {
+                    pieces.push_front((range, text));
+                    break;
+                }
+                write!(
+                    out,
+                    "{}",
+                    &found.line[printed..range.start.min(end).max(printed)]
+                )?;
+                if replaces {
+                    self.paint(out, MATCH_COLOR, &text)?;
+                    printed = range.end;
+                } else {
+                    let painted = range.start.min(end)..range.end.min(end);
+                    self.paint(out, MATCH_COLOR, &found.line[painted])?;
+                    printed = range.end.min(next);
+                    // Paint the rest of the match on the next lines
+                    if range.end > next {
+                        pieces.push_front((next..range.end, text));
+                    }
+                }
+            }
+            writeln!(out, "{}", &found.line[printed.min(end)..end])?;
+            start = next;
+        }
+        Ok(())
+    }
+
+    /// Prints the matches of a selected line, each on a line of its own with
+    /// its own prefix, leaving out empty matches and lines of context.
+    fn matches(&self, out: &mut impl Write, path: Option<&str>, line: &Context) -> io::Result<()> {
+        let Context::Match(found) = line else {
+            return Ok(());
+        };
+        for (range, text) in self.pieces(line) {
+            if range.is_empty() {
+                continue;
+            }
+            let before = &found.line[..range.start];
+            let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+            self.prefix(
+                out,
+                path,
+                ":",
+                found.line_no + before.matches('\n').count(),
+                Some(range.start - line_start + 1),
+                found.byte_offset + range.start,
+            )?;
+            self.paint(out, MATCH_COLOR, &text)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Prints the prefix of a line, with the column of its first match if
+    /// given.
+    fn prefix(
+        &self,
+        out: &mut impl Write,
+        path: Option<&str>,
+        separator: &str,
+        line_no: usize,
+        column: Option<usize>,
+        byte_offset: usize,
+    ) -> io::Result<()> {
+        if let Some(path) = path {
+            self.paint(out, PATH_COLOR, path)?;
+            self.paint(out, SEPARATOR_COLOR, separator)?;
+        }
+        // Editors expect a line number along with the column
+        if self.config.line_number || self.config.column {
+            self.paint(out, NUMBER_COLOR, &line_no.to_string())?;
+            self.paint(out, SEPARATOR_COLOR, separator)?;
+        }
+        if let (true, Some(column)) = (self.config.column, column) {
+            self.paint(out, NUMBER_COLOR, &column.to_string())?;
+            self.paint(out, SEPARATOR_COLOR, separator)?;
+        }
+        if self.config.byte_offset {
+            self.paint(out, NUMBER_COLOR, &byte_offset.to_string())?;
+            self.paint(out, SEPARATOR_COLOR, separator)?;
+        }
+        Ok(())
+    }
+
+    /// Prints the lines changed by `--in-place` in the file at `path`, as a
+    /// unified diff.
+    pub fn diff(&self, out: &mut impl Write, path: &str, changes: &[Change]) -> io::Result<()> {
+        let mut changes = changes.iter().filter(|change| change.old != change.new);
+        let Some(first) = changes.next() else {
+            return Ok(());
+        };
+        self.paint(out, PATH_COLOR, &format!("--- {}", path))?;
+        writeln!(out)?;
+        self.paint(out, PATH_COLOR, &format!("+++ {}", path))?;
+        writeln!(out)?;
+        for change in std::iter::once(first).chain(changes) {
+            let line_no = change.line_no;
+            self.paint(
+                out,
+                SEPARATOR_COLOR,
+                &format!("@@ -{} +{} @@", line_no, line_no),
+            )?;
+            writeln!(out)?;
+            writeln!(out, "-{}", change.old)?;
+            writeln!(out, "+{}", change.new)?;
+        }
+        Ok(())
+    }
+
+    /// Tells whether the matches of `line` are replaced when printing it.
+    fn replaces(&self, line: &Context) -> bool {
+        matches!(line, Context::Match(_)) && self.config.replace.is_some()
+    }
+
+    /// Returns the byte ranges of the matches in `line`, along with what to
+    /// print in their place.
+    fn pieces<'l>(&self, line: &Context<'l>) -> Vec<(Range<usize>, Cow<'l, str>)> {
+        let (found, matches) = match line {
+            Context::Match(found) => (found, found.matches.clone()),
+            // Context lines match too when selecting the lines not matching
+            Context::Line(found) => (found, self.matcher.find_iter(found.line)),
+        };
+        match &self.config.replace {
+            Some(replacement) if self.replaces(line) => self
+                .matcher
+                .replacements(found.line, replacement)
+                .into_iter()
+                .map(|(range, text)| (range, Cow::Owned(text)))
+                .collect(),
+            _ => matches
+                .into_iter()
+                .map(|range| (range.clone(), Cow::Borrowed(&found.line[range])))
+                .collect(),
+        }
+    }
+
+    /// Prints the event starting the lines of the file at `path`, in JSON.
+    pub fn begin(&self, out: &mut impl Write, path: &str) -> io::Result<()> {
+        self.event(out, &Event::Begin { path })
+    }
+
+    /// Prints the event ending the lines of the file at `path`, in JSON.
+    pub fn end(&self, out: &mut impl Write, path: &str, stats: FileStats) -> io::Result<()> {
+        self.event(out, &Event::End { path, stats })
+    }
+
+    /// Prints the statistics of the whole search, in JSON.
+    pub fn summary(&self, out: &mut impl Write, stats: Stats) -> io::Result<()> {
+        self.event(out, &Event::Summary { stats })
+    }
+
+    fn json_line(&self, out: &mut impl Write, path: &str, line: &Context) -> io::Result<()> {
+        let found = match line {
+            Context::Match(found) | Context::Line(found) => found,
+        };
+        let replaces = self.replaces(line);
+        let distances = if self.matcher.is_fuzzy() {
+            self.matcher.find_iter_with_distances(found.line)
+        } else {
+            Vec::new()
+        };
+        let submatches = self
+            .pieces(line)
+            .into_iter()
+            .map(|(range, text)| Submatch {
+                text: &found.line[range.clone()],
+                start: range.start,
+                end: range.end,
+                replacement: replaces.then(|| text.into_owned()),
+                distance: distances
+                    .iter()
+                    .find(|(found, _)| *found == range)
+                    .map(|&(_, distance)| distance),
+            })
+            .collect();
+        let line_event = Line {
+            path,
+            line_number: found.line_no,
+            absolute_offset: found.byte_offset,
+            lines: found.line,
+            submatches,
+        };
+        self.event(
+            out,
+            &match line {
+                Context::Match(_) => Event::Match(line_event),
+                Context::Line(_) => Event::Context(line_event),
+            },
+        )
+    }
+
+    fn event(&self, out: &mut impl Write, event: &Event) -> io::Result<()> {
+        serde_json::to_writer(&mut *out, event)?;
+        writeln!(out)
+    }
+
+    fn paint(&self, out: &mut impl Write, color: &str, text: &str) -> io::Result<()> {
+        if self.color && !text.is_empty() {
+            write!(out, "\x1b[{}m{}\x1b[0m", color, text)
+        } else {
+            write!(out, "{}", text)
+        }
+    }
+}
+
+/// Why searching a file failed.
+pub enum FileError {
+    Read(io::Error),
+    Rewrite(io::Error),
+    Write(io::Error),
+}
+
+/// Errors of reading the file, as the others are told apart where they
+/// happen.
+impl From<io::Error> for FileError {
+    fn from(err: io::Error) -> FileError {
+        FileError::Read(err)
+    }
+}
+
+/// Prints the lines of a file as a search finds them, keeping count of what
+/// was selected.
+pub struct PrintSink<'p, 'a, W> {
+    printer: &'p Printer<'a>,
+    out: W,
+    /// Name of the file in messages about it.
+    path: &'p str,
+    /// Name of the file in front of each line, if any.
+    line_path: Option<&'p str>,
+    pub stats: FileStats,
+    printed: bool,
+}
+
+impl<'p, 'a, W: Write> PrintSink<'p, 'a, W> {
+    pub fn new(
+        printer: &'p Printer<'a>,
+        out: W,
+        path: &'p str,
+        line_path: Option<&'p str>,
+    ) -> PrintSink<'p, 'a, W> {
+        PrintSink {
+            printer,
+            out,
+            path,
+            line_path,
+            stats: FileStats::default(),
+            printed: false,
+        }
+    }
+}
+
+impl<W: Write> Sink for PrintSink<'_, '_, W> {
+    type Error = FileError;
+
+    fn line(&mut self, line: &Context) -> Result<bool, FileError> {
+        let json = self.printer.config.json;
+        if let Context::Match(found) = line {
+            self.stats.matched_lines += 1;
+            if json {
+                self.stats.matches += found.matches.len();
+            }
+        }
+        if json && !self.printed {
+            self.printer
+                .begin(&mut self.out, self.path)
+                .map_err(FileError::Write)?;
+        }
+        self.printed = true;
+        self.printer
+            .line(&mut self.out, self.line_path, line)
+            .map_err(FileError::Write)?;
+        Ok(true)
+    }
+
+    fn context_break(&mut self) -> Result<bool, FileError> {
+        // Only the matches of selected lines are printed with -o, not context
+        if !self.printer.config.only_matching {
+            self.printer
+                .group_separator(&mut self.out)
+                .map_err(FileError::Write)?;
+        }
+        Ok(true)
+    }
+
+    fn finish(&mut self) -> Result<(), FileError> {
+        if self.printer.config.json && self.printed {
+            self.printer
+                .end(&mut self.out, self.path, self.stats)
+                .map_err(FileError::Write)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Match, MatcherBuilder};
+
+    #[test]
+    fn highlights_matches_and_prefixes() {
+        let config = Config {
+            line_number: true,
+            ..Config::default()
+        };
+        let matcher = Matcher::new("o+").unwrap();
+        let line = Context::Match(Match {
+            line_no: 7,
+            byte_offset: 0,
+            column: 2,
+            line: "foo boo",
+            matches: vec![1..3, 5..7],
+        });
+
+        let mut out = Vec::new();
+        Printer::new(&config, &matcher, true)
+            .line(&mut out, Some("a.txt"), &line)
+            .unwrap();
+        assert_eq!(
+            "\x1b[35ma.txt\x1b[0m\x1b[36m:\x1b[0m\x1b[32m7\x1b[0m\x1b[36m:\x1b[0m\
+             f\x1b[1;31moo\x1b[0m b\x1b[1;31moo\x1b[0m\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        let mut out = Vec::new();
+        Printer::new(&config, &matcher, false)
+            .line(&mut out, Some("a.txt"), &line)
+            .unwrap();
+        assert_eq!("a.txt:7:foo boo\n", String::from_utf8(out).unwrap());
+
+        let config = Config {
+            replace: Some("[$0]".to_string()),
+            ..config
+        };
+        let mut out = Vec::new();
+        Printer::new(&config, &matcher, false)
+            .line(&mut out, None, &line)
+            .unwrap();
+        assert_eq!("7:f[oo] b[oo]\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn multiple_lines() {
+        let config = Config {
+            line_number: true,
+            byte_offset: true,
+            ..Config::default()
+        };
+        let matcher = MatcherBuilder::new()
+            .multiline(true)
+            .build(r"o\s+b|z")
+            .unwrap();
+        let line = Context::Match(Match {
+            line_no: 7,
+            byte_offset: 10,
+            column: 3,
+            line: "foo\r\nbar\nbaz",
+            matches: vec![2..6, 11..12],
+        });
+        let print = |config: &Config, color| {
+            let mut out = Vec::new();
+            Printer::new(config, &matcher, color)
+                .line(&mut out, None, &line)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!("7:10:foo\n8:15:bar\n9:19:baz\n", print(&config, false));
+        assert_eq!(
+            "fo\x1b[1;31mo\x1b[0m\n\x1b[1;31mb\x1b[0mar\nba\x1b[1;31mz\x1b[0m\n",
+            print(&Config::default(), true)
+        );
+    }
+
+    #[test]
+    fn only_matching() {
+        let config = Config {
+            only_matching: true,
+            column: true,
+            ..Config::default()
+        };
+        let matcher = MatcherBuilder::new()
+            .multiline(true)
+            .build(r"o\s+b|z")
+            .unwrap();
+        let line = Context::Match(Match {
+            line_no: 7,
+            byte_offset: 10,
+            column: 3,
+            line: "foo\nbar\nbaz",
+            matches: vec![2..5, 10..11],
+        });
+
+        let mut out = Vec::new();
+        let printer = Printer::new(&config, &matcher, false);
+        printer.line(&mut out, Some("a.txt"), &line).unwrap();
+        assert_eq!(
+            "a.txt:7:3:o\nb\na.txt:9:3:z\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn diff() {
+        let config = Config::default();
+        let matcher = Matcher::new("o+").unwrap();
+        let change = |line_no, old: &str, new: &str| Change {
+            line_no,
+            matches: 1,
+            old: old.to_string(),
+            new: new.to_string(),
+        };
+        let changes = [
+            change(2, "foo", "f0"),
+            change(3, "o", "o"),
+            change(5, "boo", "b0"),
+        ];
+
+        let mut out = Vec::new();
+        Printer::new(&config, &matcher, false)
+            .diff(&mut out, "a.txt", &changes)
+            .unwrap();
+        assert_eq!(
+            "--- a.txt\n+++ a.txt\n@@ -2 +2 @@\n-foo\n+f0\n@@ -5 +5 @@\n-boo\n+b0\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn json_lines() {
+        let config = Config {
+            json: true,
+            ..Config::default()
+        };
+        let matcher = Matcher::new("o+").unwrap();
+        let line = Context::Line(Match {
+            line_no: 7,
+            byte_offset: 42,
+            column: 1,
+            line: "foo \"boo\"",
+            matches: vec![],
+        });
+
+        let mut out = Vec::new();
+        let printer = Printer::new(&config, &matcher, true);
+        printer.line(&mut out, Some("a.txt"), &line).unwrap();
+        printer.group_separator(&mut out).unwrap();
+        assert_eq!(
+            r#"{"type":"context","data":{"path":"a.txt","line_number":7,"absolute_offset":42,"#
+                .to_string()
+                + r#""lines":"foo \"boo\"","submatches":[{"match":"oo","start":1,"end":3},"#
+                + r#"{"match":"oo","start":6,"end":8}]}}"#
+                + "\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
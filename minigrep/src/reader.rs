@@ -0,0 +1,527 @@
+use crate::{Context, Match, Matcher};
+use memchr::{memchr, memchr_iter, memrchr};
+use memmap2::Mmap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::Path;
+
+/// Options of [`search_reader`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SearchOptions {
+    /// Selects the lines not matched, rather than the matched ones.
+    pub invert_match: bool,
+    /// Number of lines of context before each selected line.
+    pub before_context: usize,
+    /// Number of lines of context after each selected line.
+    pub after_context: usize,
+    /// Matches the whole input at once, so that matches may span lines. The
+    /// lines of each match are selected together, as a single multi-line
+    /// [`Match`]. The matcher should be built with
+    /// [`crate::MatcherBuilder::multiline`].
+    pub multiline: bool,
+    /// Stops after this many selected lines, once the lines of context after
+    /// the last one are passed.
+    pub max_count: Option<usize>,
+}
+
+/// Size of the block at the start of the input in which binary files are
+/// detected, that of the buffer of a [`BufReader`].
+const BLOCK_SIZE: usize = 8 * 1024;
+
+/// The contents of a file to search.
+pub enum Input<'a> {
+    /// A regular file mapped in memory, searched all at once.
+    Mapped(Mmap),
+    /// Any other file, searched a line at a time.
+    Buffered(Box<dyn BufRead + 'a>),
+}
+
+impl Input<'_> {
+    /// Opens the file at `path`, mapping it in memory if `map` is set and it
+    /// is a non-empty regular file. Falls back to reading it through a
+    /// buffer when mapping fails.
+    pub fn open(path: &Path, map: bool) -> io::Result<Input<'static>> {
+        let file = File::open(path)?;
+        if map {
+            let metadata = file.metadata()?;
+            if metadata.is_file() && metadata.len() > 0 {
+                // SAFETY: files changed while mapped may be read inconsistently
+                // or, if truncated, crash the process, a risk taken by grep
+                // tools for the speed of searching a file all at once
+                if let Ok(map) = unsafe { Mmap::map(&file) } {
+                    return Ok(Input::Mapped(map));
+                }
+            }
+        }
+        Ok(Input::Buffered(Box::new(BufReader::new(file))))
+    }
+
+    /// Returns the first block of the contents, without consuming it.
+    pub fn start(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Input::Mapped(map) => Ok(&map[..map.len().min(BLOCK_SIZE)]),
+            Input::Buffered(reader) => reader.fill_buf(),
+        }
+    }
+
+    /// Returns the whole contents, read into `buf` unless mapped.
+    pub fn contents<'b>(&'b mut self, buf: &'b mut Vec<u8>) -> io::Result<&'b [u8]> {
+        match self {
+            Input::Mapped(map) => Ok(map),
+            Input::Buffered(reader) => {
+                reader.read_to_end(buf)?;
+                Ok(buf)
+            }
+        }
+    }
+
+    /// Searches the contents with [`search_slice`] if mapped, or else with
+    /// [`search_reader`].
+    pub fn search(
+        self,
+        matcher: &Matcher,
+        options: SearchOptions,
+        sink: impl FnMut(Context) -> bool,
+    ) -> io::Result<()> {
+        match self {
+            Input::Mapped(map) => {
+                search_slice(&map, matcher, options, sink);
+                Ok(())
+            }
+            Input::Buffered(reader) => search_reader(reader, matcher, options, sink),
+        }
+    }
+}
+
+/// Searches the lines read from `reader`, passing the selected lines and
+/// the lines of context around them to `sink`, in order. The search stops
+/// early once `sink` returns `false`.
+///
+/// Lines are read one at a time, so memory use is bounded by the longest
+/// line and `before_context`, not by the size of the input, unless matching
+/// multiple lines, which reads the whole input at once. Invalid UTF-8 is
+/// replaced with U+FFFD, which may shift the columns of later matches on
+/// the same line, but not the byte offsets of lines.
+pub fn search_reader<R: BufRead>(
+    mut reader: R,
+    matcher: &Matcher,
+    options: SearchOptions,
+    sink: impl FnMut(Context) -> bool,
+) -> io::Result<()> {
+    if options.multiline {
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents)?;
+        search_multiline(&String::from_utf8_lossy(&contents), matcher, options, sink);
+        return Ok(());
+    }
+    let mut selector = Selector::new(options, sink);
+    let mut buf = Vec::new();
+    let mut line_no = 0;
+    let mut next_byte_offset = 0;
+
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            return Ok(());
+        }
+        line_no += 1;
+        let byte_offset = next_byte_offset;
+        next_byte_offset += read;
+
+        let line = String::from_utf8_lossy(trim_terminator(&buf));
+        let matches = matcher.find_iter(&line);
+        if !selector.line(line_no, byte_offset, &line, matches) {
+            return Ok(());
+        }
+    }
+}
+
+/// Searches the lines of `contents` like [`search_reader`], with the same
+/// results.
+///
+/// Rather than matching every line, looks for the literal every match starts
+/// with in all of `contents` at once, if the matcher has one, and only
+/// matches the lines where it is found, along with their lines of context.
+pub fn search_slice(
+    contents: &[u8],
+    matcher: &Matcher,
+    options: SearchOptions,
+    sink: impl FnMut(Context) -> bool,
+) {
+    if options.multiline {
+        search_multiline(&String::from_utf8_lossy(contents), matcher, options, sink);
+        return;
+    }
+    let mut selector = Selector::new(options, sink);
+    // Lines without the prefix only matter when selecting lines not matching
+    let prefix = matcher.prefix().filter(|_| !options.invert_match);
+    let mut line_no = 0;
+    // Offset of the start of the next line
+    let mut start = 0;
+
+    while start < contents.len() {
+        if let (Some(prefix), 0) = (prefix, selector.after_left) {
+            let Some(hit) = prefix.find(&contents[start..]) else {
+                return;
+            };
+            let hit_line = line_start(contents, start, start + hit);
+            // Skip to the lines of context before the line of the hit
+            let mut skip_to = hit_line;
+            for _ in 0..options.before_context {
+                if skip_to == start {
+                    break;
+                }
+                skip_to = line_start(contents, start, skip_to - 1);
+            }
+            line_no += memchr_iter(b'\n', &contents[start..skip_to]).count();
+            start = skip_to;
+        }
+
+        let end = memchr(b'\n', &contents[start..]).map_or(contents.len(), |end| start + end + 1);
+        line_no += 1;
+        let line = String::from_utf8_lossy(trim_terminator(&contents[start..end]));
+        let matches = matcher.find_iter(&line);
+        if !selector.line(line_no, start, &line, matches) {
+            return;
+        }
+        start = end;
+    }
+}
+
+/// Searches all of `contents` at once, selecting the lines of each match
+/// together, along with those of the matches they overlap, or the lines that
+/// are part of no match when inverting it.
+fn search_multiline(
+    contents: &str,
+    matcher: &Matcher,
+    options: SearchOptions,
+    sink: impl FnMut(Context) -> bool,
+) {
+    let mut selector = Selector::new(options, sink);
+    let mut matches = matcher.find_iter(contents).into_iter().peekable();
+    let mut line_no = 1;
+    // Offset of the start of the next line
+    let mut start = 0;
+
+    while start < contents.len() {
+        let line_end = |offset: usize| {
+            memchr(b'\n', &contents.as_bytes()[offset..])
+                .map_or(contents.len(), |end| offset + end + 1)
+        };
+        let mut end = line_end(start);
+        // Matches starting on the line, and on the lines they extend it to
+        let mut found = Vec::new();
+        while let Some(range) = matches.next_if(|range| range.start < end) {
+            found.push(range.clone());
+            // Extend the lines up to the one holding the end of the match
+            let last = if range.is_empty() {
+                range.start
+            } else {
+                range.end - 1
+            };
+            if last >= end {
+                end = line_end(last);
+            }
+        }
+
+        let lines = &contents[start..end];
+        if options.invert_match && !found.is_empty() {
+            // Lines of matches are not selected, but may still be context
+            for line in lines.split_inclusive('\n') {
+                let text = trim_line(line);
+                let parts = parts(&found, start..start + line.len(), text.len());
+                if !selector.line(line_no, start, text, parts) {
+                    return;
+                }
+                line_no += 1;
+                start += line.len();
+            }
+            continue;
+        }
+        let text = trim_line(lines);
+        if !selector.line(line_no, start, text, parts(&found, start..end, text.len())) {
+            return;
+        }
+        line_no += lines.split_inclusive('\n').count();
+        start = end;
+    }
+}
+
+/// Returns the parts of `matches` within `lines`, relative to their start
+/// and left out of their last line terminator, where the text of the lines
+/// is `len` bytes long.
+fn parts(matches: &[Range<usize>], lines: Range<usize>, len: usize) -> Vec<Range<usize>> {
+    matches
+        .iter()
+        .filter(|range| {
+            range.start < lines.end && (range.end > lines.start || range.start >= lines.start)
+        })
+        .map(|range| {
+            let start = (range.start.max(lines.start) - lines.start).min(len);
+            start..(range.end.min(lines.end) - lines.start).clamp(start, len)
+        })
+        .collect()
+}
+
+/// Returns the offset of the start of the line holding `offset` in
+/// `contents`, looking no further back than `min`.
+fn line_start(contents: &[u8], min: usize, offset: usize) -> usize {
+    memrchr(b'\n', &contents[min..offset]).map_or(min, |newline| min + newline + 1)
+}
+
+/// Strips the line terminator ending `line`, as [`trim_terminator`] does.
+pub(crate) fn trim_line(line: &str) -> &str {
+    // Only ASCII is stripped, which leaves valid UTF-8
+    &line[..trim_terminator(line.as_bytes()).len()]
+}
+
+/// Strips the `\n` or `\r\n` ending `line`.
+pub(crate) fn trim_terminator(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Selects the lines of a search one at a time, passing them to a sink
+/// along with the lines of context around them.
+pub(crate) struct Selector<F> {
+    options: SearchOptions,
+    sink: F,
+    /// Most recent unselected lines, as (line number, byte offset, line).
+    before: VecDeque<(usize, usize, String)>,
+    /// Number of lines of context left to pass after the last selected line.
+    after_left: usize,
+    /// Number of lines selected so far.
+    selected: usize,
+}
+
+impl<F: FnMut(Context) -> bool> Selector<F> {
+    pub(crate) fn new(options: SearchOptions, sink: F) -> Selector<F> {
+        Selector {
+            options,
+            sink,
+            before: VecDeque::new(),
+            after_left: 0,
+            selected: 0,
+        }
+    }
+
+    /// Selects `line` or keeps it as context, given the byte ranges of its
+    /// matches. Returns `false` once the sink asks to stop, or once there is
+    /// nothing left to pass to it after `max_count` selected lines.
+    pub(crate) fn line(
+        &mut self,
+        line_no: usize,
+        byte_offset: usize,
+        line: &str,
+        matches: Vec<Range<usize>>,
+    ) -> bool {
+        let limited = self
+            .options
+            .max_count
+            .is_some_and(|max_count| self.selected >= max_count);
+        if limited && self.after_left == 0 {
+            return false;
+        }
+
+        if !limited && matches.is_empty() == self.options.invert_match {
+            for (line_no, byte_offset, line) in self.before.drain(..) {
+                let context = Context::Line(Match {
+                    line_no,
+                    byte_offset,
+                    column: 1,
+                    line: &line,
+                    matches: Vec::new(),
+                });
+                if !(self.sink)(context) {
+                    return false;
+                }
+            }
+            // Lines selected for not matching have no matches to tell of
+            let matches = if self.options.invert_match {
+                Vec::new()
+            } else {
+                matches
+            };
+            let selected = Context::Match(Match {
+                line_no,
+                byte_offset,
+                column: matches.first().map_or(1, |found| found.start + 1),
+                line,
+                matches,
+            });
+            if !(self.sink)(selected) {
+                return false;
+            }
+            self.selected += 1;
+            self.after_left = self.options.after_context;
+        } else if self.after_left > 0 {
+            self.after_left -= 1;
+            let context = Context::Line(Match {
+                line_no,
+                byte_offset,
+                column: 1,
+                line,
+                matches: Vec::new(),
+            });
+            if !(self.sink)(context) {
+                return false;
+            }
+        } else if self.options.before_context > 0 {
+            if self.before.len() == self.options.before_context {
+                self.before.pop_front();
+            }
+            self.before
+                .push_back((line_no, byte_offset, line.to_string()));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+// Lines with a single match are no mistake
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+    use crate::MatcherBuilder;
+
+    #[test]
+    fn slices_same_as_reader() {
+        let contents = "1x\n2\n3xy\n4\n5\n6\n7xy\r\n8\n9xz\n10\n\n12xy";
+        for pattern in ["xy", "xy$", "x", "x[yz]", "^"] {
+            let matcher = Matcher::new(pattern).unwrap();
+            for (invert_match, before_context, after_context) in [
+                (false, 0, 0),
+                (false, 2, 0),
+                (false, 0, 2),
+                (false, 1, 1),
+                (true, 1, 0),
+            ] {
+                let options = SearchOptions {
+                    invert_match,
+                    before_context,
+                    after_context,
+                    ..SearchOptions::default()
+                };
+                let mut streamed = Vec::new();
+                search_reader(contents.as_bytes(), &matcher, options, |line| {
+                    streamed.push(format!("{:?}", line));
+                    true
+                })
+                .unwrap();
+                let mut sliced = Vec::new();
+                search_slice(contents.as_bytes(), &matcher, options, |line| {
+                    sliced.push(format!("{:?}", line));
+                    true
+                });
+                assert_eq!(streamed, sliced, "{} {:?}", pattern, options);
+            }
+        }
+    }
+
+    #[test]
+    fn multiline() {
+        let contents = "1\n2ab\r\n3c\n4\n5a\n6bc\n7\nabc";
+        let lines = |pattern, invert_match, before_context| {
+            let matcher = MatcherBuilder::new()
+                .multiline(true)
+                .build(pattern)
+                .unwrap();
+            let options = SearchOptions {
+                invert_match,
+                before_context,
+                multiline: true,
+                ..SearchOptions::default()
+            };
+            let mut lines = Vec::new();
+            search_slice(contents.as_bytes(), &matcher, options, |line| {
+                lines.push(match line {
+                    Context::Match(found) => {
+                        (found.line_no, found.column, found.line.to_string(), true)
+                    }
+                    Context::Line(found) => {
+                        (found.line_no, found.column, found.line.to_string(), false)
+                    }
+                });
+                true
+            });
+            lines
+        };
+        let line =
+            |line_no, column, line: &str, selected| (line_no, column, line.to_string(), selected);
+
+        // Matches overlapping the same lines are selected together
+        assert_eq!(
+            vec![
+                line(2, 2, "2ab\r\n3c", true),
+                line(5, 2, "5a\n6bc", true),
+                line(8, 1, "abc", true)
+            ],
+            lines(r"a[^c]*c|b$", false, 0)
+        );
+        assert_eq!(
+            vec![line(1, 1, "1", false), line(2, 3, "2ab\r\n3c\n4", true)],
+            lines(r"b\s+3|c\n4", false, 1)
+        );
+        assert_eq!(
+            vec![
+                line(1, 1, "1", true),
+                line(4, 1, "4", true),
+                line(7, 1, "7", true)
+            ],
+            lines(r"a[^c]*c", true, 0)
+        );
+    }
+
+    #[test]
+    fn max_count() {
+        let matcher = Matcher::new("x").unwrap();
+        let contents = "1x\n2x\n3x\n4\n5x";
+        let lines = |max_count, after_context| {
+            let options = SearchOptions {
+                after_context,
+                max_count: Some(max_count),
+                ..SearchOptions::default()
+            };
+            let mut lines = Vec::new();
+            search_slice(contents.as_bytes(), &matcher, options, |line| {
+                lines.push(match line {
+                    Context::Match(found) => (found.line_no, found.matches),
+                    Context::Line(found) => (found.line_no, found.matches),
+                });
+                true
+            });
+            lines
+        };
+
+        assert_eq!(vec![(1, vec![1..2]), (2, vec![1..2])], lines(2, 0));
+        // Matching lines after the last one are context
+        assert_eq!(vec![(1, vec![1..2]), (2, vec![]), (3, vec![])], lines(1, 2));
+        assert!(lines(0, 1).is_empty());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let matcher = Matcher::new("b").unwrap();
+        let mut lines = Vec::new();
+        search_reader(
+            &b"a\xffb\nb"[..],
+            &matcher,
+            SearchOptions::default(),
+            |line| {
+                if let Context::Match(found) = line {
+                    lines.push((found.line.to_string(), found.byte_offset));
+                }
+                true
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![("a\u{fffd}b".to_string(), 0), ("b".to_string(), 4)],
+            lines
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Rewriting files with the matches in their lines replaced.
+
+use crate::reader::trim_terminator;
+use crate::Matcher;
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A line matched by [`replace_lines`].
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    /// Number of the line, starting at 1.
+    pub line_no: usize,
+    /// Number of matches in the line.
+    pub matches: usize,
+    /// The line before and after the replacements, without its line
+    /// terminator. Both are the same if the matches were replaced by what
+    /// they matched.
+    pub old: String,
+    pub new: String,
+}
+
+/// Replaces the matches of `matcher` in the lines of `contents` with
+/// `replacement`, as told by [`Matcher::replacements`].
+///
+/// Returns the new contents along with the lines that matched. Line
+/// terminators are kept as they are, and so are lines that are not valid
+/// UTF-8.
+pub fn replace_lines(
+    contents: &[u8],
+    matcher: &Matcher,
+    replacement: &str,
+) -> (Vec<u8>, Vec<Change>) {
+    let mut replaced = Vec::with_capacity(contents.len());
+    let mut changes = Vec::new();
+    for (index, line) in contents.split_inclusive(|&byte| byte == b'\n').enumerate() {
+        let text = trim_terminator(line);
+        let matches = std::str::from_utf8(text)
+            .map(|text| (text, matcher.find_iter(text).len()))
+            .ok()
+            .filter(|&(_, matches)| matches > 0);
+        match matches {
+            Some((text, matches)) => {
+                let new = matcher.replace(text, replacement);
+                replaced.extend_from_slice(new.as_bytes());
+                replaced.extend_from_slice(&line[text.len()..]);
+                changes.push(Change {
+                    line_no: index + 1,
+                    matches,
+                    old: text.to_string(),
+                    new,
+                });
+            }
+            None => replaced.extend_from_slice(line),
+        }
+    }
+    (replaced, changes)
+}
+
+/// Replaces the contents of the file at `path` with `contents`, such that
+/// readers either see the old contents or the new ones.
+///
+/// The contents are written to a temporary file next to the original one,
+/// with the same permissions, which is then renamed over it. Symbolic links
+/// are followed, so that the file they point to is replaced rather than the
+/// link itself.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let permissions = fs::metadata(&path)?.permissions();
+    // Counted across the process, so that threads rewriting the same file
+    // start from different names
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let (temp, mut file) = create_temp(&path, COUNTER.fetch_add(1, Ordering::Relaxed))?;
+    let result =
+        write_temp(&mut file, permissions, contents).and_then(|()| fs::rename(&temp, &path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Creates a new temporary file next to `path`, named after it and `count`.
+///
+/// Names already taken, like those left by a process that was killed, are
+/// skipped by counting up.
+fn create_temp(path: &Path, mut count: usize) -> io::Result<(PathBuf, File)> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    loop {
+        let temp = path.with_file_name(format!(".{}.minigrep-{}-{}", name, process::id(), count));
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((temp, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => count += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn write_temp(file: &mut File, permissions: Permissions, contents: &[u8]) -> io::Result<()> {
+    file.write_all(contents)?;
+    file.set_permissions(permissions)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn keeps_line_terminators_and_invalid_utf8() {
+        let matcher = Matcher::new("(a)b").unwrap();
+        let contents = b"ab\r\nxy\nab \xff\nabab";
+        let (replaced, changes) = replace_lines(contents, &matcher, "<$1>");
+
+        assert_eq!(b"<a>\r\nxy\nab \xff\n<a><a>".as_slice(), replaced);
+        assert_eq!(
+            vec![(1, 1, "<a>"), (4, 2, "<a><a>")],
+            changes
+                .iter()
+                .map(|change| (change.line_no, change.matches, change.new.as_str()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn writes_atomically() {
+        let dir = TestDir::new("replace");
+        let path = dir.write("file", "old");
+        write_atomically(&path, b"new").unwrap();
+        assert_eq!("new", fs::read_to_string(&path).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_symlinks() {
+        let dir = TestDir::new("symlink");
+        let (target, link) = (dir.write("target", "old"), dir.path().join("link"));
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomically(&link, b"new").unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!("new", fs::read_to_string(&target).unwrap());
+    }
+
+    #[test]
+    fn skips_stale_temp_files() {
+        let dir = TestDir::new("stale");
+        let path = dir.path().join("target");
+        // Left behind by an earlier run that was killed
+        dir.write(&format!(".target.minigrep-{}-7", process::id()), "");
+
+        let (temp, _) = create_temp(&path, 7).unwrap();
+        assert_eq!(
+            dir.path()
+                .join(format!(".target.minigrep-{}-8", process::id())),
+            temp
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Searching any input with a matcher, reporting the lines found to a
+//! [`Sink`].
+
+use crate::reader::Input;
+use crate::{search_reader, search_slice, Context, Matcher, SearchOptions};
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+/// Receives the lines found by a [`Searcher`], in order.
+///
+/// The error type lets sinks fail with errors of their own, like those of
+/// writing the lines somewhere, while errors of reading the input are
+/// converted from [`io::Error`].
+pub trait Sink {
+    type Error: From<io::Error>;
+
+    /// Receives a selected line, or a line of context around one. Returns
+    /// whether to go on searching.
+    fn line(&mut self, line: &Context) -> Result<bool, Self::Error>;
+
+    /// Called between groups of lines that are not adjacent, when searching
+    /// with lines of context. Returns whether to go on searching.
+    fn context_break(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    /// Called once the search is over, even if the sink stopped it early,
+    /// unless it failed.
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Searches inputs for the lines selected by a [`Matcher`] with the same
+/// [`SearchOptions`].
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::{Context, MatcherBuilder, SearchOptions, Searcher, Sink};
+/// use std::io;
+///
+/// /// Collects the numbers of the selected lines.
+/// struct LineNumbers(Vec<usize>);
+///
+/// impl Sink for LineNumbers {
+///     type Error = io::Error;
+///
+///     fn line(&mut self, line: &Context) -> io::Result<bool> {
+///         if let Context::Match(found) = line {
+///             self.0.push(found.line_no);
+///         }
+///         Ok(true)
+///     }
+/// }
+///
+/// let matcher = MatcherBuilder::new().build("rust").unwrap();
+/// let searcher = Searcher::new(matcher, SearchOptions::default());
+/// let mut sink = LineNumbers(Vec::new());
+/// searcher.search_reader("rust\nis\nrust".as_bytes(), &mut sink).unwrap();
+/// assert_eq!(sink.0, [1, 3]);
+/// ```
+pub struct Searcher {
+    matcher: Matcher,
+    options: SearchOptions,
+}
+
+impl Searcher {
+    pub fn new(matcher: Matcher, options: SearchOptions) -> Searcher {
+        Searcher { matcher, options }
+    }
+
+    pub fn matcher(&self) -> &Matcher {
+        &self.matcher
+    }
+
+    pub fn options(&self) -> SearchOptions {
+        self.options
+    }
+
+    /// Searches the lines read from `reader` as [`search_reader`] does.
+    pub fn search_reader<S: Sink>(&self, reader: impl Read, sink: &mut S) -> Result<(), S::Error> {
+        self.report(sink, |lines| {
+            search_reader(BufReader::new(reader), &self.matcher, self.options, lines)
+        })
+    }
+
+    /// Searches the lines of `contents` as [`search_slice`] does.
+    pub fn search_slice<S: Sink>(&self, contents: &[u8], sink: &mut S) -> Result<(), S::Error> {
+        self.report(sink, |lines| {
+            search_slice(contents, &self.matcher, self.options, lines);
+            Ok(())
+        })
+    }
+
+    /// Searches the file at `path`, mapping it in memory if it is a regular
+    /// file.
+    pub fn search_path<S: Sink>(&self, path: &Path, sink: &mut S) -> Result<(), S::Error> {
+        self.search_input(Input::open(path, true)?, sink)
+    }
+
+    pub(crate) fn search_input<S: Sink>(&self, input: Input, sink: &mut S) -> Result<(), S::Error> {
+        self.report(sink, |lines| {
+            input.search(&self.matcher, self.options, lines)
+        })
+    }
+
+    /// Passes the lines found by `search` to `sink`, along with the breaks
+    /// between groups of lines.
+    fn report<S: Sink>(
+        &self,
+        sink: &mut S,
+        search: impl FnOnce(&mut dyn FnMut(Context) -> bool) -> io::Result<()>,
+    ) -> Result<(), S::Error> {
+        let with_context = self.options.before_context > 0 || self.options.after_context > 0;
+        let mut last_line_no = None;
+        let mut result = Ok(true);
+        search(&mut |line| {
+            let (Context::Match(found) | Context::Line(found)) = &line;
+            if with_context && last_line_no.is_some_and(|last| last + 1 < found.line_no) {
+                result = sink.context_break();
+            }
+            last_line_no = Some(found.last_line_no());
+            if let Ok(true) = result {
+                result = sink.line(&line);
+            }
+            matches!(result, Ok(true))
+        })?;
+        result?;
+        sink.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatcherBuilder;
+
+    /// Records what it receives, stopping after `limit` lines.
+    struct Recorder {
+        events: Vec<String>,
+        limit: usize,
+    }
+
+    impl Sink for Recorder {
+        type Error = io::Error;
+
+        fn line(&mut self, line: &Context) -> io::Result<bool> {
+            self.events.push(match line {
+                Context::Match(found) => format!("{}:{}", found.line_no, found.line),
+                Context::Line(found) => format!("{}-{}", found.line_no, found.line),
+            });
+            Ok(self.events.len() < self.limit)
+        }
+
+        fn context_break(&mut self) -> io::Result<bool> {
+            self.events.push("--".to_string());
+            Ok(true)
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            self.events.push("end".to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sink_events() {
+        let contents = "rust\na\nb\nc\nrust\nd\n";
+        let options = SearchOptions {
+            after_context: 1,
+            ..SearchOptions::default()
+        };
+        let searcher = Searcher::new(MatcherBuilder::new().build("rust").unwrap(), options);
+        let expected = ["1:rust", "2-a", "--", "5:rust", "6-d", "end"];
+
+        let mut sink = Recorder {
+            events: Vec::new(),
+            limit: usize::MAX,
+        };
+        searcher
+            .search_reader(contents.as_bytes(), &mut sink)
+            .unwrap();
+        assert_eq!(sink.events, expected);
+
+        let mut sink = Recorder {
+            events: Vec::new(),
+            limit: 3,
+        };
+        searcher
+            .search_slice(contents.as_bytes(), &mut sink)
+            .unwrap();
+        assert_eq!(sink.events, ["1:rust", "2-a", "--", "5:rust", "end"]);
+    }
+}
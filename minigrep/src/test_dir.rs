@@ -0,0 +1,43 @@
+//! Temporary directories for tests that write files.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A directory of its own for a test, removed along with its files when
+/// dropped, even if the test fails.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    /// Creates an empty directory named after `name`, which must be unique
+    /// among tests, and the process.
+    pub fn new(name: &str) -> TestDir {
+        let path = env::temp_dir().join(format!("minigrep-{}-{}", name, process::id()));
+        // Left behind by a run that was killed
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TestDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `contents` to the file at `name` in the directory, creating
+    /// its parent directories, and returns its path.
+    pub fn write(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,198 @@
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::types::{Types, TypesBuilder};
+use ignore::WalkBuilder;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Which files to search below directories.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Filters {
+    /// Searches hidden files and directories too.
+    pub hidden: bool,
+    /// Disregards `.gitignore`, `.ignore` and similar files.
+    pub no_ignore: bool,
+    /// Globs of files to search, or to skip when starting with `!`.
+    pub globs: Vec<String>,
+    /// Types of files to search, like `rust`.
+    pub types: Vec<String>,
+}
+
+/// Walks directories, skipping the files ruled out by [`Filters`].
+pub struct Walker {
+    filters: Filters,
+    overrides: Override,
+    types: Types,
+}
+
+impl Walker {
+    /// # Errors
+    ///
+    /// Fails if a glob is invalid or a file type unknown.
+    pub fn new(filters: &Filters) -> Result<Walker, ignore::Error> {
+        // Globs are relative to the current directory, like paths
+        let mut overrides = OverrideBuilder::new(env::current_dir().unwrap_or_default());
+        for glob in &filters.globs {
+            overrides.add(glob)?;
+        }
+        let mut types = TypesBuilder::new();
+        types.add_defaults();
+        for name in &filters.types {
+            types.select(name);
+        }
+        Ok(Walker {
+            filters: filters.clone(),
+            overrides: overrides.build()?,
+            types: types.build()?,
+        })
+    }
+
+    /// Collects the files at or below `path` into `files`, descending into
+    /// directories recursively. Entries are visited in name order.
+    ///
+    /// By default, hidden files and those ignored by `.gitignore`, `.ignore`
+    /// and similar files are skipped, but never `path` itself.
+    /// Entries that cannot be read are passed to `on_error` and skipped, so a
+    /// single unreadable directory does not end the walk.
+    /// Symbolic links to directories are only followed when given as `path`,
+    /// which rules out cycles.
+    pub fn walk(
+        &self,
+        path: &Path,
+        files: &mut Vec<PathBuf>,
+        on_error: &mut dyn FnMut(&Path, ignore::Error),
+    ) {
+        let respect_ignore = !self.filters.no_ignore;
+        let walk = WalkBuilder::new(path)
+            .hidden(!self.filters.hidden)
+            .parents(respect_ignore)
+            .ignore(respect_ignore)
+            .git_ignore(respect_ignore)
+            .git_global(respect_ignore)
+            .git_exclude(respect_ignore)
+            .overrides(self.overrides.clone())
+            .types(self.types.clone())
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+
+        for entry in walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    let (path, err) = with_path(path, err);
+                    on_error(&path, err);
+                    continue;
+                }
+            };
+            let is_file = match entry.file_type() {
+                Some(file_type) if file_type.is_symlink() => match fs::metadata(entry.path()) {
+                    Ok(metadata) => metadata.is_file(),
+                    Err(err) => {
+                        on_error(entry.path(), err.into());
+                        continue;
+                    }
+                },
+                Some(file_type) => file_type.is_file(),
+                // Standard input
+                None => true,
+            };
+            if is_file {
+                files.push(entry.into_path());
+            }
+        }
+    }
+}
+
+/// Splits the path an error is about from the error, or else attributes the
+/// error to `root`.
+fn with_path(root: &Path, err: ignore::Error) -> (PathBuf, ignore::Error) {
+    match err {
+        ignore::Error::WithPath { path, err } => (path, *err),
+        ignore::Error::WithDepth { err, .. } => with_path(root, *err),
+        err => (root.to_path_buf(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn walk(root: &Path, filters: Filters) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        Walker::new(&filters)
+            .unwrap()
+            .walk(root, &mut files, &mut |path, _| {
+                errors.push(path.to_path_buf())
+            });
+        (files, errors)
+    }
+
+    #[test]
+    fn walks_recursively_in_order() {
+        let dir = TestDir::new("walk");
+        let root = dir.path();
+        dir.write("b/c/d.txt", "d");
+        dir.write("b/a.txt", "a");
+        dir.write("e.txt", "e");
+
+        let (files, _) = walk(root, Filters::default());
+        let (_, errors) = walk(&root.join("missing"), Filters::default());
+
+        assert_eq!(
+            files,
+            vec![
+                root.join("b/a.txt"),
+                root.join("b/c/d.txt"),
+                root.join("e.txt")
+            ]
+        );
+        assert_eq!(errors, vec![root.join("missing")]);
+    }
+
+    #[test]
+    fn filters() {
+        let dir = TestDir::new("filters");
+        let root = dir.path();
+        dir.write(".ignore", "*.log\n");
+        dir.write("a.log", "");
+        dir.write("b.rs", "");
+        dir.write("c.txt", "");
+        dir.write("target/d.rs", "");
+        dir.write(".hidden/e.rs", "");
+
+        let default = walk(root, Filters::default()).0;
+        let all = walk(
+            root,
+            Filters {
+                hidden: true,
+                no_ignore: true,
+                ..Filters::default()
+            },
+        )
+        .0;
+        let rust = walk(
+            root,
+            Filters {
+                types: vec!["rust".to_string()],
+                globs: vec!["!**/target/**".to_string()],
+                ..Filters::default()
+            },
+        )
+        .0;
+        let explicit = walk(&root.join("a.log"), Filters::default()).0;
+
+        assert_eq!(
+            default,
+            vec![
+                root.join("b.rs"),
+                root.join("c.txt"),
+                root.join("target/d.rs")
+            ]
+        );
+        assert_eq!(all.len(), 6);
+        assert_eq!(rust, vec![root.join("b.rs")]);
+        assert_eq!(explicit, vec![root.join("a.log")]);
+    }
+}
@@ -0,0 +1,47 @@
+//! Checks the `--json` output against the fixtures in `tests/fixtures`, where
+//! `NAME.jsonl` is the expected output of the arguments of `NAME` below.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn check(name: &str, args: &[&str]) {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let output = Command::new(env!("CARGO_BIN_EXE_minigrep"))
+        .args(args)
+        .current_dir(&fixtures)
+        .env_remove("CASE_INSENSITIVE")
+        .env_remove("MINIGREP_CONFIG")
+        .output()
+        .unwrap();
+    let expected = fs::read_to_string(fixtures.join(format!("{}.jsonl", name))).unwrap();
+    assert_eq!(
+        expected,
+        String::from_utf8(output.stdout).unwrap(),
+        "{}",
+        name
+    );
+}
+
+#[test]
+fn context() {
+    check(
+        "context",
+        &[
+            "--json",
+            "-C1",
+            "--sort=path",
+            "-e",
+            "body",
+            "-e",
+            "bog",
+            "poem.txt",
+            "rust.txt",
+        ],
+    );
+}
+
+#[test]
+fn invert() {
+    check("invert", &["--json", "-v", "-i", "S", "rust.txt"]);
+}
@@ -1,16 +1,47 @@
+//! Quicksort, sequential and parallel.
+//!
+//! Without the default `parallel` feature the crate is `#![no_std]` and
+//! only the sequential algorithms are available.
+
+#![cfg_attr(not(feature = "parallel"), no_std)]
+
+// The test harness needs `std`, also when the library itself does not.
+#[cfg(all(test, not(feature = "parallel")))]
+extern crate std;
+
 pub mod sort {
+    #[cfg(feature = "parallel")]
     use rayon::join;
+    #[cfg(feature = "parallel")]
     pub use rayon::prelude::*;
 
     fn pivot_hoare(arr: &mut [f64], low: usize, high: usize) -> f64 {
         arr[(low + high) / 2]
     }
 
-    /// Unstable partitioning algorithm.
+    /// Partitions `arr[low..=high]` around its middle element with Hoare's
+    /// scheme, as [`quicksort_seq`] and `quicksort` do. The partitioning is
+    /// unstable.
+    ///
+    /// Returns an index `p` in `low..high`, or `low` if `low == high`, such
+    /// that every element of `arr[low..=p]` is <= every element of
+    /// `arr[p + 1..=high]`. Unlike with Lomuto's scheme, the pivot itself
+    /// need not end up at `p`, so both sides still include it when sorting.
+    ///
+    /// # Panics
     ///
-    /// Return the index of the pivot, such that the left partition is <= the
-    /// pivot and the right partition is > the pivot.
-    fn partition_hoare(arr: &mut [f64], low: usize, high: usize) -> usize {
+    /// Panics if `low > high` or `high` is out of the bounds of `arr`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use quicksort::sort::partition_hoare;
+    /// let mut arr = [1.0, 7.1, 2.2, 8.0];
+    /// let p = partition_hoare(&mut arr, 0, 3);
+    /// assert_eq!(p, 1);
+    /// assert_eq!(arr, [1.0, 2.2, 7.1, 8.0]);
+    /// ```
+    pub fn partition_hoare(arr: &mut [f64], low: usize, high: usize) -> usize {
         let pivot = pivot_hoare(arr, low, high);
         // Set indices taking possible overflows into account
         let (mut left, mut skip_left) = if low == usize::MIN {
@@ -78,6 +109,8 @@ pub mod sort {
     /// be used if they are available and idle.
     /// Performs an unstable sort.
     ///
+    /// Requires the `parallel` feature.
+    ///
     /// # Examples
     ///
     /// ```
@@ -86,6 +119,7 @@ pub mod sort {
     /// quicksort(&mut arr);
     /// assert_eq!(arr, [3.0, 3.0, 3.0, 7.0, 9.0]);
     /// ```
+    #[cfg(feature = "parallel")]
     pub fn quicksort(arr: &mut [f64]) {
         let low = 0;
         let high = arr.len() - 1;
@@ -100,10 +134,17 @@ pub mod sort {
         join(|| quicksort(left), || quicksort(right));
     }
 
+    // -3.14 is test data, not an approximation of pi
     #[cfg(test)]
+    #[allow(
+        clippy::approx_constant,
+        clippy::needless_return,
+        clippy::needless_range_loop
+    )]
     mod tests {
         use super::*;
         use rand::Rng;
+        use std::vec::Vec;
 
         fn is_sorted(arr: &[f64]) -> bool {
             for i in 1..arr.len() {
@@ -232,13 +273,16 @@ pub mod sort {
             assert_eq!(arr, [-3.14, 1.0, 3.4, 4.0, 9.7]);
         }
 
+        #[cfg(feature = "parallel")]
         #[test]
+        #[allow(clippy::clone_on_copy, clippy::unit_cmp)]
         fn quicksort_serial() {
             let mut arr1 = [1.0, 9.7, 3.4, 4.0];
             let mut arr2 = arr1.clone();
             assert_eq!(quicksort_seq(&mut arr1), quicksort(&mut arr2));
         }
 
+        #[cfg(feature = "parallel")]
         #[test]
         fn quicksort_even_unsorted_concurrent() {
             let mut arr = [1.0, 9.7, 3.4, 4.0];
@@ -246,6 +290,7 @@ pub mod sort {
             assert_eq!(arr, [1.0, 3.4, 4.0, 9.7]);
         }
 
+        #[cfg(feature = "parallel")]
         #[test]
         fn quicksort_odd_unsorted_concurrent() {
             let mut arr = [1.0, 9.7, 3.4, 4.0, -3.14];
@@ -257,13 +302,22 @@ pub mod sort {
         /// `min` to `max`, inclusive.
         fn gen_rnd_vec(size: usize, min: f64, max: f64) -> Vec<f64> {
             let mut rng = rand::thread_rng();
-            let mut vec = vec![0.0; size];
+            let mut vec = std::vec![0.0; size];
             for i in 0..size {
                 vec[i] = rng.gen_range(min..=max);
             }
             vec
         }
 
+        #[test]
+        fn quicksort_seq_big() {
+            let mut vec = gen_rnd_vec(100_000, 0.0, 100.0);
+            let arr = &mut vec[..];
+            quicksort_seq(arr);
+            assert!(is_sorted(arr));
+        }
+
+        #[cfg(feature = "parallel")]
         #[test]
         fn quicksort_big_concurrent() {
             let mut vec = gen_rnd_vec(100_000, 0.0, 100.0);
@@ -1,7 +1,7 @@
-mod lib;
-
 use quicksort::sort::{quicksort, quicksort_seq};
 
+// -3.14 is sample data, not an approximation of pi
+#[allow(clippy::approx_constant)]
 fn main() {
     let mut vec1: Vec<f64> = vec![1.0, 9.7, 3.4, 4.0, -3.14];
     let mut vec2 = vec1.clone();